@@ -28,6 +28,63 @@ impl fmt::Display for ExifStructureErr {
     }
 }
 
+#[derive(Debug, Clone)]
+pub(crate) struct PngStructureErr {
+    info: String
+}
+
+impl PngStructureErr {
+    pub(crate) fn new(info: &str) -> PngStructureErr {
+        PngStructureErr {
+            info: info.to_string()
+        }
+    }
+}
+impl Error for PngStructureErr {}
+impl fmt::Display for PngStructureErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "broken png structure: {}", self.info)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PdfStructureErr {
+    info: String
+}
+
+impl PdfStructureErr {
+    pub(crate) fn new(info: &str) -> PdfStructureErr {
+        PdfStructureErr {
+            info: info.to_string()
+        }
+    }
+}
+impl Error for PdfStructureErr {}
+impl fmt::Display for PdfStructureErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pdf not purged: {}", self.info)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PdfEncryptedErr {
+    info: String
+}
+
+impl PdfEncryptedErr {
+    pub(crate) fn new(info: &str) -> PdfEncryptedErr {
+        PdfEncryptedErr {
+            info: info.to_string()
+        }
+    }
+}
+impl Error for PdfEncryptedErr {}
+impl fmt::Display for PdfEncryptedErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "encrypted pdf: {}", self.info)
+    }
+}
+
 
 
 
@@ -88,6 +145,12 @@ impl ToUISideErr for ZipError {
     }
 }
 
+impl ToUISideErr for quick_xml::Error {
+    fn to_user(&self, context: String) -> UISideErr {
+        UISideErr{path: context, info: format!("Error parsing document properties: {}", self) }
+    }
+}
+
 impl ToUISideErr for lopdf::Error {
     fn to_user(&self, context: String) -> UISideErr {
          UISideErr{path:context, info: "Error parsing pdf".parse().unwrap() }
@@ -116,17 +179,36 @@ impl ToUISideErr for ExifStructureErr {
         UISideErr{path: context , info: self.info.clone() }
     }
 }
+impl ToUISideErr for PngStructureErr {
+    fn to_user(&self, context: String) -> UISideErr {
+        UISideErr{path: context , info: self.to_string() }
+    }
+}
+impl ToUISideErr for PdfStructureErr {
+    fn to_user(&self, context: String) -> UISideErr {
+        UISideErr{path: context , info: self.to_string() }
+    }
+}
+impl ToUISideErr for PdfEncryptedErr {
+    fn to_user(&self, context: String) -> UISideErr {
+        UISideErr{path: context , info: self.to_string() }
+    }
+}
 
 #[derive(Debug)]
 pub(crate) enum PurgeErr {
     IoError(io::Error),
     XmpError(XmpError),
     ZipError(ZipError),
+    XmlError(quick_xml::Error),
     LopdfError(lopdf::Error),
     UTF8Error(Utf8Error),
     SendErrOut(SendError<OutMessage>),
     DirError(walkdir::Error),
-    ExifError(ExifStructureErr)
+    ExifError(ExifStructureErr),
+    PngError(PngStructureErr),
+    PdfError(PdfStructureErr),
+    PdfEncrypted(PdfEncryptedErr)
 }
 
 
@@ -148,6 +230,12 @@ impl From<ZipError> for PurgeErr {
     }
 }
 
+impl From<quick_xml::Error> for PurgeErr {
+    fn from(error: quick_xml::Error) -> Self {
+        PurgeErr::XmlError(error)
+    }
+}
+
 impl From<lopdf::Error> for PurgeErr {
     fn from(error: lopdf::Error) -> Self {
         PurgeErr::LopdfError(error)
@@ -179,6 +267,24 @@ impl From<ExifStructureErr> for PurgeErr {
     }
 }
 
+impl From<PngStructureErr> for PurgeErr {
+    fn from(error: PngStructureErr) -> Self {
+        PurgeErr::PngError(error)
+    }
+}
+
+impl From<PdfStructureErr> for PurgeErr {
+    fn from(error: PdfStructureErr) -> Self {
+        PurgeErr::PdfError(error)
+    }
+}
+
+impl From<PdfEncryptedErr> for PurgeErr {
+    fn from(error: PdfEncryptedErr) -> Self {
+        PurgeErr::PdfEncrypted(error)
+    }
+}
+
 ///
 
 pub trait ToUser<T> {
@@ -191,11 +297,15 @@ impl ToUser<UISideErr> for PurgeErr {
             PurgeErr::IoError(e) => e.to_user(context),
             PurgeErr::XmpError(e) => e.to_user(context),
             PurgeErr::ZipError(e) => e.to_user(context),
+            PurgeErr::XmlError(e) => e.to_user(context),
             PurgeErr::LopdfError(e) => e.to_user(context),
             PurgeErr::UTF8Error(e) => e.to_user(context),
             PurgeErr::SendErrOut(e) => e.to_user(context),
             PurgeErr::DirError(e) => e.to_user(context),
             PurgeErr::ExifError(e) => {e.to_user(context)}
+            PurgeErr::PngError(e) => e.to_user(context),
+            PurgeErr::PdfError(e) => e.to_user(context),
+            PurgeErr::PdfEncrypted(e) => e.to_user(context),
         }
     }
 }
@@ -1,196 +1,226 @@
+mod segments;
+mod app;
+mod exif;
+mod tags;
+mod trailer;
+mod mpf;
+
 use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
+use std::ops::Range;
 use crate::errors::error::PurgeErr;
+use crate::jpeg::app::{AppKind, AppPolicy};
+use crate::jpeg::exif::Exif;
+use crate::jpeg::mpf::{MpImage, MpIndex, MPF_HEADER};
+use crate::jpeg::tags::TagPolicy;
+use crate::jpeg::trailer::TrailerKind;
+use crate::options::OPTIONS;
+use crate::jpeg::segments::SegmentKind;
 use crate::traits::container::{DataPaths, Heaped};
 
-#[derive(Debug, Copy, Clone)]
-struct Range {
-    start: u16,
-    end: u16
+pub(crate) struct Jpg {
+    paths: DataPaths,
+    data: Vec<u8>,
+    cleaner: Cleaner,
+    report: Vec<String>
 }
 
-impl Range {
-    fn new(start: u16, end: u16) -> Self {
-        Range {
-            start,
-            end,
-        }
-    }
+/// What to keep of a JPEG, and the cleaning itself, apart from any file. PDFs use it for their embedded images.
+#[derive(Clone)]
+pub(crate) struct Cleaner {
+    policy: AppPolicy,
+    scrub: Option<TagPolicy>,
+    keep_orientation: bool,
+    keep_resolution: bool,
+    keep_trailer: bool
+}
 
+fn app1_segment(payload: &[u8]) -> Option<Vec<u8>> {
+    let length = u16::try_from(payload.len() + 2).ok()?;
+    let mut segment = vec![0xff, 0xe1];
+    segment.extend_from_slice(&length.to_be_bytes());
+    segment.extend_from_slice(payload);
+    Some(segment)
+}
 
+/// Where the payload of the kept MPF segment is, in the source and in the cleaned output.
+struct MpfSegment {
+    source: Range<usize>,
+    output: usize,
 }
 
-fn dont_take_ranges(src: &Vec<u8>, ranges: Vec<Range>) -> Vec<u8> {
+impl Cleaner {
+    pub(crate) fn from_options() -> Cleaner {
+        Cleaner {
+            // Kept trailing images are only found through the MPF index.
+            policy: if OPTIONS.keep_trailer { AppPolicy::default().with(AppKind::Mpf) } else { AppPolicy::default() },
+            scrub: OPTIONS.scrub_exif.then(|| TagPolicy::from_lists(
+                &OPTIONS.exif_allow,
+                &OPTIONS.exif_deny,
+                &OPTIONS.exif_blank)),
+            keep_orientation: OPTIONS.keep_orientation,
+            keep_resolution: OPTIONS.keep_resolution,
+            keep_trailer: OPTIONS.keep_trailer
+        }
+    }
 
-    let mut src_iter = src.iter().enumerate();
-    let mut rg_iter = ranges.iter();
+    /// APP1 segment to put in place of a dropped EXIF block, if the options ask for one.
+    /// A block we can't parse is dropped like any other EXIF.
+    fn replace_exif(&self, payload: &[u8], report: &mut Vec<String>) -> Option<Vec<u8>> {
+        let mut exif = Exif::parse(payload).ok()?;
+        let exif = match &self.scrub {
+            Some(policy) => {
+                exif.scrub(policy);
+                if let Some(size) = exif.drop_thumbnail() {
+                    report.push(format!("removed EXIF thumbnail ({} bytes)", size));
+                }
+                exif
+            },
+            None if self.keep_orientation => exif.display_only(self.keep_resolution)?,
+            None => return None,
+        };
+        app1_segment(&exif.to_payload())
+    }
 
-    let mut clean_buf: Vec<u8> = Vec::new();
+    /// Cleans the secondary images of a multi-picture file, which follow the first image at `tail`,
+    /// and appends them to `out` with whatever else is in between. Their sizes and offsets are
+    /// updated in the copy of the MPF segment in `out`. Returns `None`, leaving `out` alone, if
+    /// the MP index doesn't describe images after the first one.
+    fn clean_mpf_images(&self, data: &[u8], tail: usize, mpf: &MpfSegment, out: &mut Vec<u8>) -> Option<Vec<String>> {
+        let index = MpIndex::parse(&data[mpf.source.clone()])?;
+        let source_base = mpf.source.start + MPF_HEADER.len();
+        let mut images: Vec<(usize, Range<usize>)> = index.images.iter()
+            .enumerate()
+            .filter(|(_, image)| image.offset != 0)
+            .map(|(n, image)| {
+                let start = source_base + image.offset as usize;
+                (n, start..start + image.size as usize)
+            })
+            .collect();
+        images.sort_by_key(|(_, range)| range.start);
+        let fits = images.iter()
+            .try_fold(tail, |pos, (_, range)| (range.start >= pos && range.end <= data.len()).then_some(range.end))
+            .is_some();
+        if images.is_empty() || !fits {
+            return None
+        }
 
-    while let Some(range) = rg_iter.next() {
-        let start = range.start;
-        let end = range.end;
-        while let Some((index, data)) = src_iter.next() {
-            if index < start as usize {
-                clean_buf.push(data.clone())
-            } else if index == end as usize {
-                {
-                    break
-                }
+        let secondary = Cleaner { keep_trailer: false, ..self.clone() };
+        let output_base = mpf.output + MPF_HEADER.len();
+        let mut patches: Vec<(usize, MpImage)> = index.images.iter()
+            .position(|image| image.offset == 0)
+            .map(|n| (n, MpImage { size: out.len() as u32, offset: 0 }))
+            .into_iter()
+            .collect();
+        let mut report: Vec<String> = Vec::new();
+        let mut pos = tail;
+        for (n, range) in images {
+            out.extend_from_slice(&data[pos..range.start]);
+            let start = out.len();
+            let image = &data[range.clone()];
+            match secondary.clean(image) {
+                Ok((cleaned, lines)) => {
+                    out.extend_from_slice(&cleaned);
+                    report.extend(lines.into_iter().map(|line| format!("MPF image {}: {}", n + 1, line)));
+                },
+                Err(_) => {
+                    out.extend_from_slice(image);
+                    report.push(format!("MPF image {}: kept uncleaned, it isn't a valid JPEG", n + 1));
+                },
             }
+            patches.push((n, MpImage { size: (out.len() - start) as u32, offset: (start - output_base) as u32 }));
+            pos = range.end;
         }
+        out.extend_from_slice(&data[pos..]);
 
-    };
-    while let Some ((_, data)) = src_iter.next() {
-        clean_buf.push(data.clone())
-    };
-
-    clean_buf
-}
-
-
-fn get_app_ranges(src: &Vec<u8>) -> Vec<Range> {
-
-    let mut has_SOI: bool = false;
-
-    let mut app_indices: Vec<Range> = Vec::new();
-
-
-    let mut file_iter = src.windows(2).enumerate();
-    while let Some((index,twobytes)) = file_iter.next() {
-        match twobytes {
-            [0xff, 0xe0..=0xef] => {
-                if let Some(_) = file_iter.next() {
-                    if let Some (real_win) = file_iter.next() {
-                        let length = u16::from_be_bytes([real_win.1[0], real_win.1[1]]);
-                        let app_index_start: u16 = index as u16;
-                        let app_index_end = app_index_start + length;
+        for (n, image) in patches {
+            index.patch(&mut out[mpf.output..], n, image);
+        }
+        Some(report)
+    }
 
-                        if let Some(end_i) = app_indices.last() {
-                            if app_index_end < end_i.end {
+    /// Returns the cleaned JPEG and what was removed from it.
+    pub(crate) fn clean(&self, data: &[u8]) -> Result<(Vec<u8>, Vec<String>), PurgeErr> {
+        let segments = segments::walk(data)?;
+        let tail = segments.last().map_or(0, |segment| segment.range.end);
+
+        let mut report: Vec<String> = Vec::new();
+        let mut removed: Vec<AppKind> = Vec::new();
+        let mut comments = 0;
+        let mut rewrote_exif = false;
+        let mut mpf: Option<MpfSegment> = None;
+
+        let mut clean_buf: Vec<u8> = Vec::with_capacity(data.len());
+        for segment in segments.iter() {
+            match segment.kind {
+                SegmentKind::App(n) => {
+                    let payload = segment.payload(data);
+                    let kind = AppKind::classify(n, payload);
+                    if !self.policy.keeps(kind) {
+                        if kind == AppKind::Exif {
+                            if let Some(replacement) = self.replace_exif(payload, &mut report) {
+                                clean_buf.extend_from_slice(&replacement);
+                                if !rewrote_exif {
+                                    rewrote_exif = true;
+                                    report.push("rewrote EXIF".to_owned());
+                                }
                                 continue
                             }
                         }
-
-                        let app_range = Range::new(app_index_start, app_index_end);
-                        // println!("{:?} with app {:#02x},", app_range.clone(), app);
-                        app_indices.push(app_range);
-                    }
-                }
-            },
-            [0xff, 0xd8] => {
-                if !has_SOI{
-                    has_SOI = true;
-                    // println!("found SOI at {:?}", index);
-                    continue
-                }
-            },
-            [0xff, 0xda] => {
-                let mut SOS_walker = file_iter.clone();
-
-
-                let mut validity_buf: Vec<u8> = Vec::new();
-
-                let _ = SOS_walker.next();
-
-                for _ in 0..=4 {
-                    validity_buf.push(SOS_walker.next().unwrap().1[0])
-                }
-
-                let ns = validity_buf[2];
-                if ns & 0b0000_0011 != ns {
-                    continue
-                }
-
-                let t_d_a_j =  validity_buf[4];
-                if t_d_a_j & 0b0011_0011 != t_d_a_j {
-                    continue
-                }
-                // println!("found SOS at {:?}", index);
-            },
-            [0xff, 0xc0..=0xc3] => {
-                let mut SOF_walker = file_iter.clone();
-
-                let mut validity_buf: Vec<u8> = Vec::new();
-                let _ = SOF_walker.next();
-
-                for _ in 0..=10 {
-                    validity_buf.push(SOF_walker.next().unwrap().1[0])
-                }
-
-                let length = &validity_buf[0..=1];
-
-                if let h_n_v_sampl = &validity_buf[9].clone() {
-                    if h_n_v_sampl & 0b0011_0011 != *h_n_v_sampl {
+                        if !removed.contains(&kind) {
+                            removed.push(kind);
+                        }
                         continue
                     }
-
-                }
-
-                if let quant_tab_sel = &validity_buf[10].clone() {
-                    if quant_tab_sel & 0b0000_0011 != *quant_tab_sel {
-                        continue
+                    if kind == AppKind::Mpf && mpf.is_none() {
+                        let start = segment.range.end - payload.len();
+                        mpf = Some(MpfSegment {
+                            source: start..segment.range.end,
+                            output: clean_buf.len() + start - segment.range.start,
+                        });
                     }
-                }
-
-                // let sof_len = u16::from_be_bytes([length[0], length[1]]);
-
-                // let _ = file_iter.nth(sof_len as usize + index);
-
-                // println!("found SOF at {:?}", index);
-                continue
-            },
-            [0xff, 0xdb] => {
-                let mut DQT_walker = file_iter.clone();
-
-
-                let mut validity_buf: Vec<u8> = Vec::new();
-
-                let _ = DQT_walker.next();
-
-                for _ in 0..=4 {
-                    validity_buf.push(DQT_walker.next().unwrap().1[0])
-                }
-
-                let p_t_q = validity_buf[2];
-                if p_t_q & 0b0001_0011 != p_t_q {
+                },
+                // Comments carry software names, usernames and editing notes.
+                SegmentKind::Com => {
+                    comments += 1;
                     continue
-                }
-                // println!("found DQT  at {index}");
-            },
-            [0xff, 0xc4] => {
-                let mut DHT_walker = file_iter.clone();
-
-
-                let mut validity_buf: Vec<u8> = Vec::new();
-
-                let _ = DHT_walker.next();
-
-                for _ in 0..=4 {
-                    validity_buf.push(DHT_walker.next().unwrap().1[0])
-                }
+                },
+                _ => {}
+            }
+            clean_buf.extend_from_slice(&data[segment.range.clone()]);
+        }
 
-                let p_t_q = validity_buf[2];
-                if p_t_q & 0b0001_0011 != p_t_q {
-                    continue
+        let trailer = &data[tail..];
+        if !trailer.is_empty() {
+            let kind = TrailerKind::classify(trailer);
+            if self.keep_trailer {
+                let trailer_start = clean_buf.len();
+                let images = mpf.as_ref().and_then(|mpf| self.clean_mpf_images(data, tail, mpf, &mut clean_buf));
+                match images {
+                    Some(images) => report.extend(images),
+                    None => {
+                        clean_buf.extend_from_slice(trailer);
+                        report.push("data after end of image kept uncleaned".to_owned());
+                    },
                 }
-                // println!("found DHT  at {index}");
-            },
-            [0xff, 0xd9] => {
-
-                // println!("found ffd9 at {:?}", index);
-                continue},
-            _ => {} ,
+                // Cleaned MPF images are smaller than they were.
+                report.push(format!("kept {} bytes after end of image ({:?})", clean_buf.len() - trailer_start, kind));
+            } else {
+                report.push(format!("removed {} bytes after end of image ({:?})", trailer.len(), kind));
+            }
         }
-    }
 
-    app_indices
-}
+        if !removed.is_empty() {
+            let kinds = removed.iter().map(|kind| format!("{:?}", kind)).collect::<Vec<String>>();
+            report.insert(0, format!("removed APP segments: {}", kinds.join(", ")));
+        }
+        if comments != 0 {
+            report.push(format!("removed {} comment segment(s)", comments));
+        }
 
-pub(crate) struct Jpg {
-    paths: DataPaths,
-    data: Vec<u8>
+        Ok((clean_buf, report))
+    }
 }
 
 
@@ -199,7 +229,9 @@ impl Heaped for Jpg {
     fn new(paths: DataPaths) -> Box<Jpg> {
         Box::from(Jpg {
             paths: paths,
-            data: Vec::new()
+            data: Vec::new(),
+            cleaner: Cleaner::from_options(),
+            report: Vec::new()
         })
     }
 
@@ -217,8 +249,9 @@ impl Heaped for Jpg {
     }
 
     fn process(&mut self) -> Result<(), PurgeErr> {
-        let ranges = get_app_ranges(&self.data);
-        self.data = dont_take_ranges(&self.data, ranges);
+        let (clean_buf, report) = self.cleaner.clean(&self.data)?;
+        self.data = clean_buf;
+        self.report = report;
 
         Ok(())
     }
@@ -239,4 +272,93 @@ impl Heaped for Jpg {
         // We still have to remove the temp it remove() fails
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn report(&self) -> Vec<String> {
+        self.report.clone()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXIF: &[u8] = b"Exif\0\0II\x2a\x00\x08\x00\x00\x00\x01\x00\x25\x88\x04\x00\x01\x00\x00\x00\x1a\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+
+    fn segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xff, marker];
+        segment.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+        segment.extend_from_slice(payload);
+        segment
+    }
+
+    fn mpf_payload(primary_size: u32, secondary_size: u32, secondary_offset: u32) -> Vec<u8> {
+        let mut payload = b"MPF\0II\x2a\x00\x08\x00\x00\x00\x01\x00\x02\xb0\x07\x00\x20\x00\x00\x00\x1a\x00\x00\x00\x00\x00\x00\x00".to_vec();
+        for (attribute, size, offset) in [(0x2003_0000u32, primary_size, 0), (0, secondary_size, secondary_offset)] {
+            payload.extend_from_slice(&attribute.to_le_bytes());
+            payload.extend_from_slice(&size.to_le_bytes());
+            payload.extend_from_slice(&offset.to_le_bytes());
+            payload.extend_from_slice(&[0; 4]);
+        }
+        payload
+    }
+
+    /// A first image with EXIF and an MP index, followed by a secondary image with its own EXIF.
+    fn multi_picture() -> Vec<u8> {
+        let mut secondary = vec![0xff, 0xd8];
+        secondary.extend_from_slice(&segment(0xe1, EXIF));
+        secondary.extend_from_slice(&[0xff, 0xd9]);
+
+        let mut primary = vec![0xff, 0xd8];
+        primary.extend_from_slice(&segment(0xe1, EXIF));
+        let mpf_base = primary.len() + 4 + MPF_HEADER.len();
+        let primary_size = primary.len() + 4 + mpf_payload(0, 0, 0).len() + 2;
+        primary.extend_from_slice(&segment(0xe2, &mpf_payload(primary_size as u32, secondary.len() as u32, (primary_size - mpf_base) as u32)));
+        primary.extend_from_slice(&[0xff, 0xd9]);
+        assert_eq!(primary.len(), primary_size);
+
+        primary.extend_from_slice(&secondary);
+        primary
+    }
+
+    fn cleaner(keep_trailer: bool) -> Cleaner {
+        Cleaner {
+            policy: if keep_trailer { AppPolicy::default().with(AppKind::Mpf) } else { AppPolicy::default() },
+            scrub: None,
+            keep_orientation: false,
+            keep_resolution: false,
+            keep_trailer,
+        }
+    }
+
+    #[test]
+    fn kept_mpf_images_are_cleaned_and_relocated() {
+        let (out, report) = cleaner(true).clean(&multi_picture()).expect("cleans");
+        assert!(report.iter().any(|line| line == "MPF image 2: removed APP segments: Exif"), "{:?}", report);
+
+        let segments = segments::walk(&out).expect("output walks");
+        let primary_end = segments.last().map(|segment| segment.range.end).expect("segments");
+        let mpf = segments.iter()
+            .find(|segment| segment.kind == SegmentKind::App(2))
+            .expect("mpf kept");
+        let mpf_base = mpf.range.end - mpf.payload(&out).len() + MPF_HEADER.len();
+        assert!(segments.iter().all(|segment| segment.kind != SegmentKind::App(1)));
+
+        let index = MpIndex::parse(mpf.payload(&out)).expect("index parses");
+        assert_eq!(index.images[0], MpImage { size: primary_end as u32, offset: 0 });
+        let start = mpf_base + index.images[1].offset as usize;
+        assert_eq!(start, primary_end);
+        let secondary = &out[start..start + index.images[1].size as usize];
+        assert_eq!(start + secondary.len(), out.len());
+        let secondary_segments = segments::walk(secondary).expect("secondary walks");
+        assert!(secondary_segments.iter().all(|segment| segment.kind != SegmentKind::App(1)));
+
+        let kept = format!("kept {} bytes after end of image", out.len() - primary_end);
+        assert!(report.iter().any(|line| line.starts_with(&kept)), "{:?}", report);
+    }
+
+    #[test]
+    fn mpf_and_trailer_are_removed_by_default() {
+        let data = multi_picture();
+        let (out, _) = cleaner(false).clean(&data).expect("cleans");
+        assert_eq!(out, vec![0xff, 0xd8, 0xff, 0xd9]);
+    }
+}
@@ -0,0 +1,103 @@
+/// What an APPn segment carries, told apart by its number and identifier string.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum AppKind {
+    Jfif,
+    Jfxx,
+    Exif,
+    Xmp,
+    ExtendedXmp,
+    Icc,
+    Mpf,
+    Ducky,
+    PhotoshopIrb,
+    Adobe,
+    Unknown(u8),
+}
+
+const SIGNATURES: [(u8, &[u8], AppKind); 10] = [
+    (0, b"JFIF\0", AppKind::Jfif),
+    (0, b"JFXX\0", AppKind::Jfxx),
+    (1, b"Exif\0", AppKind::Exif),
+    (1, b"http://ns.adobe.com/xap/1.0/\0", AppKind::Xmp),
+    (1, b"http://ns.adobe.com/xmp/extension/\0", AppKind::ExtendedXmp),
+    (2, b"ICC_PROFILE\0", AppKind::Icc),
+    (2, b"MPF\0", AppKind::Mpf),
+    (12, b"Ducky", AppKind::Ducky),
+    (13, b"Photoshop 3.0\0", AppKind::PhotoshopIrb),
+    (14, b"Adobe", AppKind::Adobe),
+];
+
+impl AppKind {
+    pub(crate) fn classify(app: u8, payload: &[u8]) -> AppKind {
+        SIGNATURES.iter()
+            .find(|(n, signature, _)| *n == app && payload.starts_with(signature))
+            .map_or(AppKind::Unknown(app), |(_, _, kind)| *kind)
+    }
+}
+
+/// Which APP segments survive purging.
+///
+/// The default keeps what decoders need to render the image the same way:
+/// JFIF density, the ICC colour profile and Adobe's APP14 colour transform flag.
+/// Everything else, unknown segments included, is dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct AppPolicy {
+    keep: Vec<AppKind>,
+}
+
+impl Default for AppPolicy {
+    fn default() -> Self {
+        AppPolicy {
+            keep: vec![AppKind::Jfif, AppKind::Icc, AppKind::Adobe],
+        }
+    }
+}
+
+impl AppPolicy {
+    /// Also keeps `kind`.
+    pub(crate) fn with(mut self, kind: AppKind) -> AppPolicy {
+        if !self.keeps(kind) {
+            self.keep.push(kind);
+        }
+        self
+    }
+
+    pub(crate) fn keeps(&self, kind: AppKind) -> bool {
+        self.keep.contains(&kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_by_number_and_signature() {
+        assert_eq!(AppKind::classify(0, b"JFIF\0\x01\x02\0\0\x01\0\x01\0\0"), AppKind::Jfif);
+        assert_eq!(AppKind::classify(2, b"ICC_PROFILE\0\x01\x01"), AppKind::Icc);
+        assert_eq!(AppKind::classify(14, b"Adobe\0\x64\0\0\0\0\x01"), AppKind::Adobe);
+        assert_eq!(AppKind::classify(1, b"http://ns.adobe.com/xap/1.0/\0<x:xmpmeta/>"), AppKind::Xmp);
+        assert_eq!(AppKind::classify(1, b"Exif\0\0II*\0"), AppKind::Exif);
+    }
+
+    #[test]
+    fn signatures_only_count_in_their_own_segment() {
+        assert_eq!(AppKind::classify(1, b"JFIF\0"), AppKind::Unknown(1));
+        assert_eq!(AppKind::classify(0, b"Exif\0\0"), AppKind::Unknown(0));
+        assert_eq!(AppKind::classify(1, b"Exif"), AppKind::Unknown(1));
+        assert_eq!(AppKind::classify(15, b""), AppKind::Unknown(15));
+    }
+
+    #[test]
+    fn default_policy_keeps_only_rendering_segments() {
+        let policy = AppPolicy::default();
+        for kind in [AppKind::Jfif, AppKind::Icc, AppKind::Adobe] {
+            assert!(policy.keeps(kind), "{:?}", kind);
+        }
+        for kind in [AppKind::Jfxx, AppKind::Exif, AppKind::Xmp, AppKind::ExtendedXmp, AppKind::Mpf,
+                     AppKind::Ducky, AppKind::PhotoshopIrb, AppKind::Unknown(11)] {
+            assert!(!policy.keeps(kind), "{:?}", kind);
+        }
+        assert!(policy.with(AppKind::Mpf).keeps(AppKind::Mpf));
+    }
+}
@@ -0,0 +1,674 @@
+use std::collections::HashSet;
+use crate::errors::error::{ExifStructureErr, PurgeErr};
+use crate::jpeg::tags::{TagAction, TagPolicy};
+
+/// Identifier that starts the payload of an EXIF APP1 segment, followed by a TIFF header.
+pub(crate) const EXIF_HEADER: &[u8; 6] = b"Exif\0\0";
+
+pub(crate) const ORIENTATION: u16 = 0x0112;
+pub(crate) const X_RESOLUTION: u16 = 0x011a;
+pub(crate) const Y_RESOLUTION: u16 = 0x011b;
+pub(crate) const RESOLUTION_UNIT: u16 = 0x0128;
+pub(crate) const COLOR_SPACE: u16 = 0xa001;
+
+pub(crate) const EXIF_IFD: u16 = 0x8769;
+pub(crate) const GPS_IFD: u16 = 0x8825;
+pub(crate) const INTEROP_IFD: u16 = 0xa005;
+
+const THUMBNAIL_OFFSET: u16 = 0x0201;
+const THUMBNAIL_LENGTH: u16 = 0x0202;
+
+const POINTER_TAGS: [u16; 3] = [EXIF_IFD, GPS_IFD, INTEROP_IFD];
+const LONG: u16 = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum ByteOrder {
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    /// Byte order of a TIFF header, which starts every EXIF block and the MPF index.
+    pub(crate) fn of_tiff(header: &[u8]) -> Option<ByteOrder> {
+        match header.get(..4) {
+            Some([0x49, 0x49, 0x2a, 0x00]) => Some(ByteOrder::Little),
+            Some([0x4d, 0x4d, 0x00, 0x2a]) => Some(ByteOrder::Big),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn u16(self, bytes: &[u8]) -> u16 {
+        match self {
+            ByteOrder::Little => u16::from_le_bytes([bytes[0], bytes[1]]),
+            ByteOrder::Big => u16::from_be_bytes([bytes[0], bytes[1]]),
+        }
+    }
+
+    pub(crate) fn u32(self, bytes: &[u8]) -> u32 {
+        match self {
+            ByteOrder::Little => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            ByteOrder::Big => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+
+    fn put_u16(self, out: &mut Vec<u8>, value: u16) {
+        match self {
+            ByteOrder::Little => out.extend_from_slice(&value.to_le_bytes()),
+            ByteOrder::Big => out.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+
+    fn put_u32(self, out: &mut Vec<u8>, value: u32) {
+        match self {
+            ByteOrder::Little => out.extend_from_slice(&value.to_le_bytes()),
+            ByteOrder::Big => out.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+
+    pub(crate) fn patch_u32(self, out: &mut [u8], at: usize, value: u32) {
+        let bytes = match self {
+            ByteOrder::Little => value.to_le_bytes(),
+            ByteOrder::Big => value.to_be_bytes(),
+        };
+        out[at..at + 4].copy_from_slice(&bytes);
+    }
+}
+
+fn type_size(typ: u16) -> Option<usize> {
+    match typ {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 | 13 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+/// A single IFD entry. `data` holds the value bytes in the byte order of the source file,
+/// whether they were stored inline or behind an offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Entry {
+    pub(crate) tag: u16,
+    pub(crate) typ: u16,
+    pub(crate) count: u32,
+    pub(crate) data: Vec<u8>,
+}
+
+/// An image file directory. Pointers to the EXIF, GPS and Interop sub-IFDs are not kept
+/// as entries; the sub-IFDs are parsed into `children` and their pointers regenerated on write.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Ifd {
+    pub(crate) entries: Vec<Entry>,
+    pub(crate) children: Vec<(u16, Ifd)>,
+}
+
+impl Entry {
+    /// First value of a SHORT or LONG entry, which is how offsets and lengths are stored.
+    fn value_u32(&self, order: ByteOrder) -> u32 {
+        match (self.typ, self.data.len()) {
+            (3, 2..) => order.u16(&self.data) as u32,
+            (4, 4..) => order.u32(&self.data),
+            _ => 0,
+        }
+    }
+}
+
+impl Ifd {
+    pub(crate) fn get(&self, tag: u16) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.tag == tag)
+    }
+
+    pub(crate) fn child(&self, tag: u16) -> Option<&Ifd> {
+        self.children.iter()
+            .find(|(pointer, _)| *pointer == tag)
+            .map(|(_, ifd)| ifd)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.children.iter().all(|(_, child)| child.is_empty())
+    }
+
+    fn blank(&mut self) {
+        for entry in self.entries.iter_mut() {
+            entry.data.fill(0);
+        }
+        for (_, child) in self.children.iter_mut() {
+            child.blank();
+        }
+    }
+
+    /// Applies `policy` to the entries (when `ruled`) and to the sub-IFDs.
+    /// Only the EXIF sub-IFD shares the tag namespace of IFD0, so GPS and Interop
+    /// entries are never matched against tag rules. Their pointers are, fallback included,
+    /// so an allow list drops them unless they are on it. The EXIF sub-IFD is kept unless
+    /// ruled otherwise, its entries decide for themselves.
+    fn scrub(&mut self, policy: &TagPolicy, ruled: bool) {
+        if ruled {
+            self.entries.retain_mut(|entry| match policy.action(entry.tag) {
+                TagAction::Keep => true,
+                TagAction::Blank => {
+                    entry.data.fill(0);
+                    true
+                },
+                TagAction::Drop => false,
+            });
+        }
+        self.children.retain_mut(|(pointer, child)| {
+            let action = if *pointer == EXIF_IFD {
+                policy.rule(EXIF_IFD).unwrap_or(TagAction::Keep)
+            } else {
+                policy.action(*pointer)
+            };
+            match action {
+                TagAction::Keep => child.scrub(policy, *pointer == EXIF_IFD),
+                TagAction::Blank => child.blank(),
+                TagAction::Drop => return false,
+            }
+            !child.is_empty()
+        });
+    }
+}
+
+/// Parsed TIFF structure of an EXIF block.
+///
+/// IFD1 describes the embedded thumbnail; the thumbnail bytes are lifted out of the
+/// TIFF data so they can be relocated when the block is written back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Exif {
+    pub(crate) order: ByteOrder,
+    pub(crate) ifd0: Ifd,
+    pub(crate) ifd1: Option<Ifd>,
+    pub(crate) thumbnail: Option<Vec<u8>>,
+}
+
+fn mangled(info: &str) -> PurgeErr {
+    PurgeErr::from(ExifStructureErr::new(info))
+}
+
+impl Exif {
+    /// Parses the payload of an EXIF APP1 segment, `Exif\0\0` header included.
+    pub(crate) fn parse(payload: &[u8]) -> Result<Exif, PurgeErr> {
+        let tiff = payload.strip_prefix(EXIF_HEADER.as_slice())
+            .ok_or_else(|| mangled("missing exif header"))?;
+        let order = match ByteOrder::of_tiff(tiff) {
+            Some(order) if tiff.len() >= 8 => order,
+            _ => return Err(mangled("invalid tiff header in exif")),
+        };
+        let ifd0_offset = order.u32(&tiff[4..8]) as usize;
+
+        let mut visited = HashSet::new();
+        let (ifd0, ifd1_offset) = parse_ifd(tiff, order, ifd0_offset, &mut visited)?;
+
+        let mut ifd1 = None;
+        let mut thumbnail = None;
+        if ifd1_offset != 0 {
+            let (mut ifd, _) = parse_ifd(tiff, order, ifd1_offset, &mut visited)?;
+            let offset = ifd.get(THUMBNAIL_OFFSET).map(|entry| entry.value_u32(order));
+            let length = ifd.get(THUMBNAIL_LENGTH).map(|entry| entry.value_u32(order));
+            if let (Some(offset), Some(length)) = (offset, length) {
+                let range = offset as usize..offset as usize + length as usize;
+                thumbnail = Some(tiff.get(range)
+                    .ok_or_else(|| mangled("exif thumbnail out of bounds"))?
+                    .to_vec());
+            }
+            ifd.entries.retain(|entry| entry.tag != THUMBNAIL_OFFSET);
+            ifd1 = Some(ifd);
+        }
+
+        Ok(Exif { order, ifd0, ifd1, thumbnail })
+    }
+
+    /// Serialises back into an APP1 payload, `Exif\0\0` header included.
+    pub(crate) fn to_payload(&self) -> Vec<u8> {
+        let order = self.order;
+        let mut out: Vec<u8> = Vec::new();
+        match order {
+            ByteOrder::Little => out.extend_from_slice(&[0x49, 0x49, 0x2a, 0x00]),
+            ByteOrder::Big => out.extend_from_slice(&[0x4d, 0x4d, 0x00, 0x2a]),
+        }
+        order.put_u32(&mut out, 8);
+        let next_at = write_ifd(&mut out, order, &self.ifd0, None);
+        if let Some(ifd1) = &self.ifd1 {
+            pad(&mut out);
+            let offset = out.len() as u32;
+            order.patch_u32(&mut out, next_at, offset);
+            write_ifd(&mut out, order, ifd1, self.thumbnail.as_deref());
+        }
+
+        let mut payload = EXIF_HEADER.to_vec();
+        payload.extend_from_slice(&out);
+        payload
+    }
+
+    /// Drops or blanks tags according to `policy`.
+    /// The thumbnail IFD only honours explicit rules, since it needs its structural tags.
+    pub(crate) fn scrub(&mut self, policy: &TagPolicy) {
+        self.ifd0.scrub(policy, true);
+        if let Some(ifd1) = self.ifd1.as_mut() {
+            ifd1.entries.retain_mut(|entry| match policy.rule(entry.tag) {
+                _ if entry.tag == THUMBNAIL_LENGTH => true,
+                Some(TagAction::Drop) => false,
+                Some(TagAction::Blank) => {
+                    entry.data.fill(0);
+                    true
+                },
+                _ => true,
+            });
+        }
+    }
+
+    /// Removes IFD1 and the thumbnail it points to, which may still show the uncropped original.
+    /// Returns the size of the removed thumbnail.
+    pub(crate) fn drop_thumbnail(&mut self) -> Option<usize> {
+        self.ifd1 = None;
+        self.thumbnail.take().map(|thumbnail| thumbnail.len())
+    }
+
+    /// Builds a block with only the IFD0 tags that affect how the image is displayed.
+    /// Returns `None` if there is nothing worth keeping.
+    pub(crate) fn display_only(&self, keep_resolution: bool) -> Option<Exif> {
+        let mut ifd0 = Ifd::default();
+        let mut tags = vec![ORIENTATION];
+        if keep_resolution {
+            tags.extend_from_slice(&[X_RESOLUTION, Y_RESOLUTION, RESOLUTION_UNIT]);
+        }
+        ifd0.entries = self.ifd0.entries.iter()
+            .filter(|entry| tags.contains(&entry.tag))
+            .cloned()
+            .collect();
+
+        if keep_resolution {
+            if let Some(color_space) = self.ifd0.child(EXIF_IFD).and_then(|exif| exif.get(COLOR_SPACE)) {
+                let exif_ifd = Ifd {
+                    entries: vec![color_space.clone()],
+                    children: Vec::new(),
+                };
+                ifd0.children.push((EXIF_IFD, exif_ifd));
+            }
+        }
+
+        if ifd0.is_empty() {
+            return None
+        }
+        Some(Exif { order: self.order, ifd0, ifd1: None, thumbnail: None })
+    }
+}
+
+fn parse_ifd(tiff: &[u8], order: ByteOrder, offset: usize, visited: &mut HashSet<usize>) -> Result<(Ifd, usize), PurgeErr> {
+    if !visited.insert(offset) {
+        return Err(mangled("exif ifd loop"))
+    }
+    let count = tiff.get(offset..offset + 2)
+        .map(|bytes| order.u16(bytes) as usize)
+        .ok_or_else(|| mangled("exif ifd out of bounds"))?;
+    let table_end = offset + 2 + count * 12;
+    let next = tiff.get(table_end..table_end + 4)
+        .map(|bytes| order.u32(bytes) as usize)
+        .ok_or_else(|| mangled("exif ifd out of bounds"))?;
+
+    let mut ifd = Ifd::default();
+    for raw in tiff[offset + 2..table_end].chunks_exact(12) {
+        let tag = order.u16(&raw[0..2]);
+        let typ = order.u16(&raw[2..4]);
+        let count = order.u32(&raw[4..8]);
+
+        let size = match type_size(typ) {
+            Some(size) => size * count as usize,
+            // Unknown types can't be relocated safely, so they are not carried over.
+            None => continue,
+        };
+        let data = if size <= 4 {
+            raw[8..8 + size].to_vec()
+        } else {
+            let at = order.u32(&raw[8..12]) as usize;
+            match tiff.get(at..at + size) {
+                Some(data) => data.to_vec(),
+                None => return Err(mangled("exif value out of bounds")),
+            }
+        };
+
+        if POINTER_TAGS.contains(&tag) && data.len() == 4 {
+            let (child, _) = parse_ifd(tiff, order, order.u32(&data) as usize, visited)?;
+            ifd.children.push((tag, child));
+            continue
+        }
+        ifd.entries.push(Entry { tag, typ, count, data });
+    }
+
+    Ok((ifd, next))
+}
+
+fn pad(out: &mut Vec<u8>) {
+    if out.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Appends `ifd` with its values and sub-IFDs to `out`, which starts at the TIFF header.
+/// A `thumbnail` is written after the values and referenced from a regenerated offset tag.
+/// Returns the position of the next-IFD offset so the caller can chain another IFD.
+fn write_ifd(out: &mut Vec<u8>, order: ByteOrder, ifd: &Ifd, thumbnail: Option<&[u8]>) -> usize {
+    let mut fields: Vec<(u16, u16, u32, &[u8])> = ifd.entries.iter()
+        .map(|entry| (entry.tag, entry.typ, entry.count, entry.data.as_slice()))
+        .collect();
+    for (pointer, _) in ifd.children.iter() {
+        fields.push((*pointer, LONG, 1, &[0, 0, 0, 0]));
+    }
+    if thumbnail.is_some() {
+        fields.push((THUMBNAIL_OFFSET, LONG, 1, &[0, 0, 0, 0]));
+    }
+    fields.sort_by_key(|(tag, ..)| *tag);
+
+    order.put_u16(out, fields.len() as u16);
+    let mut deferred: Vec<(usize, &[u8])> = Vec::new();
+    let mut pointers: Vec<(u16, usize)> = Vec::new();
+    let mut thumbnail_at = None;
+    for (tag, typ, count, data) in fields {
+        order.put_u16(out, tag);
+        order.put_u16(out, typ);
+        order.put_u32(out, count);
+        let value_at = out.len();
+        if tag == THUMBNAIL_OFFSET && thumbnail.is_some() {
+            thumbnail_at = Some(value_at);
+            order.put_u32(out, 0);
+        } else if POINTER_TAGS.contains(&tag) && ifd.child(tag).is_some() {
+            pointers.push((tag, value_at));
+            order.put_u32(out, 0);
+        } else if data.len() <= 4 {
+            out.extend_from_slice(data);
+            out.resize(value_at + 4, 0);
+        } else {
+            deferred.push((value_at, data));
+            order.put_u32(out, 0);
+        }
+    }
+    let next_at = out.len();
+    order.put_u32(out, 0);
+
+    for (value_at, data) in deferred {
+        pad(out);
+        let offset = out.len() as u32;
+        order.patch_u32(out, value_at, offset);
+        out.extend_from_slice(data);
+    }
+    if let (Some(value_at), Some(thumbnail)) = (thumbnail_at, thumbnail) {
+        let offset = out.len() as u32;
+        order.patch_u32(out, value_at, offset);
+        out.extend_from_slice(thumbnail);
+    }
+    for (tag, value_at) in pointers {
+        pad(out);
+        let offset = out.len() as u32;
+        order.patch_u32(out, value_at, offset);
+        if let Some(child) = ifd.child(tag) {
+            write_ifd(out, order, child, None);
+        }
+    }
+
+    next_at
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAKE: u16 = 0x010f;
+    const DATE_TIME_ORIGINAL: u16 = 0x9003;
+    const ASCII: u16 = 2;
+    const SHORT: u16 = 3;
+    const RATIONAL: u16 = 5;
+
+    /// Position of IFD0's next-IFD offset in `sample`, counted from the TIFF header.
+    const IFD0_NEXT: usize = 8 + 2 + 4 * 12;
+    /// Position of the GPS pointer value in `sample`, counted from the TIFF header.
+    const GPS_POINTER: usize = 8 + 2 + 3 * 12 + 8;
+    const THUMBNAIL: [u8; 4] = [0xff, 0xd8, 0xff, 0xd9];
+
+    fn field(out: &mut Vec<u8>, order: ByteOrder, tag: u16, typ: u16, count: u32) {
+        order.put_u16(out, tag);
+        order.put_u16(out, typ);
+        order.put_u32(out, count);
+    }
+
+    /// An APP1 payload laid out by hand:
+    /// IFD0 (Make out of line, Orientation inline, EXIF and GPS pointers) at 8,
+    /// the EXIF sub-IFD with an Interop sub-IFD, the GPS sub-IFD with an out-of-line
+    /// rational, and IFD1 pointing at a 4 byte thumbnail at the very end.
+    fn sample(order: ByteOrder) -> Vec<u8> {
+        let mut out = Vec::new();
+        match order {
+            ByteOrder::Little => out.extend_from_slice(b"II\x2a\x00"),
+            ByteOrder::Big => out.extend_from_slice(b"MM\x00\x2a"),
+        }
+        order.put_u32(&mut out, 8);
+
+        order.put_u16(&mut out, 4);
+        field(&mut out, order, MAKE, ASCII, 6);
+        order.put_u32(&mut out, 62);
+        field(&mut out, order, ORIENTATION, SHORT, 1);
+        order.put_u16(&mut out, 6);
+        out.extend_from_slice(&[0, 0]);
+        field(&mut out, order, EXIF_IFD, LONG, 1);
+        order.put_u32(&mut out, 68);
+        field(&mut out, order, GPS_IFD, LONG, 1);
+        order.put_u32(&mut out, 136);
+        order.put_u32(&mut out, 190);
+        assert_eq!(out.len(), 62);
+        out.extend_from_slice(b"Canon\0");
+
+        order.put_u16(&mut out, 2);
+        field(&mut out, order, DATE_TIME_ORIGINAL, ASCII, 20);
+        order.put_u32(&mut out, 98);
+        field(&mut out, order, INTEROP_IFD, LONG, 1);
+        order.put_u32(&mut out, 118);
+        order.put_u32(&mut out, 0);
+        out.extend_from_slice(b"2024:01:01 00:00:00\0");
+
+        order.put_u16(&mut out, 1);
+        field(&mut out, order, 0x0001, ASCII, 4);
+        out.extend_from_slice(b"R98\0");
+        order.put_u32(&mut out, 0);
+
+        assert_eq!(out.len(), 136);
+        order.put_u16(&mut out, 2);
+        field(&mut out, order, 0x0001, ASCII, 2);
+        out.extend_from_slice(b"N\0\0\0");
+        field(&mut out, order, 0x0002, RATIONAL, 3);
+        order.put_u32(&mut out, 166);
+        order.put_u32(&mut out, 0);
+        for value in [52, 1, 31, 1, 1234, 100] {
+            order.put_u32(&mut out, value);
+        }
+
+        assert_eq!(out.len(), 190);
+        order.put_u16(&mut out, 2);
+        field(&mut out, order, THUMBNAIL_OFFSET, LONG, 1);
+        order.put_u32(&mut out, 220);
+        field(&mut out, order, THUMBNAIL_LENGTH, LONG, 1);
+        order.put_u32(&mut out, THUMBNAIL.len() as u32);
+        order.put_u32(&mut out, 0);
+        out.extend_from_slice(&THUMBNAIL);
+
+        let mut payload = EXIF_HEADER.to_vec();
+        payload.extend_from_slice(&out);
+        payload
+    }
+
+    fn patch(payload: &mut [u8], order: ByteOrder, at: usize, value: u32) {
+        order.patch_u32(payload, EXIF_HEADER.len() + at, value);
+    }
+
+    fn parsed(order: ByteOrder) -> Exif {
+        Exif::parse(&sample(order)).expect("sample parses")
+    }
+
+    #[test]
+    fn parses_both_byte_orders() {
+        for order in [ByteOrder::Little, ByteOrder::Big] {
+            let exif = parsed(order);
+            assert_eq!(exif.order, order);
+            assert_eq!(exif.ifd0.get(MAKE).map(|entry| entry.data.as_slice()), Some(b"Canon\0".as_slice()));
+            assert_eq!(exif.ifd0.get(ORIENTATION).map(|entry| entry.value_u32(order)), Some(6));
+
+            let exif_ifd = exif.ifd0.child(EXIF_IFD).expect("exif sub-ifd");
+            assert_eq!(exif_ifd.get(DATE_TIME_ORIGINAL).map(|entry| entry.data.len()), Some(20));
+            assert!(exif_ifd.child(INTEROP_IFD).is_some());
+
+            let gps = exif.ifd0.child(GPS_IFD).expect("gps sub-ifd");
+            let latitude = gps.get(0x0002).expect("latitude");
+            assert_eq!(latitude.data.len(), 24);
+            assert_eq!(order.u32(&latitude.data[8..12]), 31);
+
+            let ifd1 = exif.ifd1.as_ref().expect("ifd1");
+            assert!(ifd1.get(THUMBNAIL_OFFSET).is_none());
+            assert_eq!(exif.thumbnail.as_deref(), Some(THUMBNAIL.as_slice()));
+        }
+    }
+
+    #[test]
+    fn round_trips_both_byte_orders() {
+        for order in [ByteOrder::Little, ByteOrder::Big] {
+            let exif = parsed(order);
+            let payload = exif.to_payload();
+            let again = Exif::parse(&payload).expect("written block parses");
+            assert_eq!(again, exif);
+            assert_eq!(again.to_payload(), payload);
+        }
+    }
+
+    #[test]
+    fn offsets_stay_valid_after_scrubbing() {
+        for order in [ByteOrder::Little, ByteOrder::Big] {
+            let mut exif = parsed(order);
+            exif.scrub(&TagPolicy::from_lists(&[], &[ORIENTATION], &[]));
+            let payload = exif.to_payload();
+            let again = Exif::parse(&payload).expect("scrubbed block parses");
+            assert_eq!(again, exif);
+            assert_eq!(again.ifd0.get(MAKE).map(|entry| entry.data.as_slice()), Some(b"Canon\0".as_slice()));
+            assert_eq!(again.thumbnail.as_deref(), Some(THUMBNAIL.as_slice()));
+        }
+    }
+
+    #[test]
+    fn truncated_blocks_are_errors() {
+        for order in [ByteOrder::Little, ByteOrder::Big] {
+            let payload = sample(order);
+            for cut in 0..payload.len() {
+                assert!(Exif::parse(&payload[..cut]).is_err(), "cut at {}", cut);
+            }
+        }
+    }
+
+    #[test]
+    fn cyclic_ifd_chains_are_errors() {
+        for order in [ByteOrder::Little, ByteOrder::Big] {
+            let mut payload = sample(order);
+            patch(&mut payload, order, IFD0_NEXT, 8);
+            assert!(Exif::parse(&payload).is_err());
+
+            let mut payload = sample(order);
+            patch(&mut payload, order, GPS_POINTER, 8);
+            assert!(Exif::parse(&payload).is_err());
+        }
+    }
+
+    #[test]
+    fn allow_list_drops_gps_and_interop() {
+        let mut exif = parsed(ByteOrder::Little);
+        exif.scrub(&TagPolicy::from_lists(&[ORIENTATION], &[], &[]));
+        assert_eq!(exif.ifd0.entries.iter().map(|entry| entry.tag).collect::<Vec<_>>(), vec![ORIENTATION]);
+        assert!(exif.ifd0.children.is_empty());
+    }
+
+    #[test]
+    fn allow_list_keeps_gps_when_allowed() {
+        let mut exif = parsed(ByteOrder::Little);
+        exif.scrub(&TagPolicy::from_lists(&[ORIENTATION, GPS_IFD, DATE_TIME_ORIGINAL], &[], &[]));
+        assert_eq!(exif.ifd0.child(GPS_IFD).map(|gps| gps.entries.len()), Some(2));
+        let exif_ifd = exif.ifd0.child(EXIF_IFD).expect("exif sub-ifd");
+        assert!(exif_ifd.get(DATE_TIME_ORIGINAL).is_some());
+        assert!(exif_ifd.child(INTEROP_IFD).is_none());
+    }
+
+    #[test]
+    fn deny_list_drops_gps_and_listed_tags() {
+        let mut exif = parsed(ByteOrder::Big);
+        exif.scrub(&TagPolicy::from_lists(&[], &[MAKE], &[]));
+        assert!(exif.ifd0.get(MAKE).is_none());
+        assert!(exif.ifd0.get(ORIENTATION).is_some());
+        assert!(exif.ifd0.child(GPS_IFD).is_none());
+        let exif_ifd = exif.ifd0.child(EXIF_IFD).expect("exif sub-ifd");
+        assert!(exif_ifd.get(DATE_TIME_ORIGINAL).is_some());
+        assert!(exif_ifd.child(INTEROP_IFD).is_some());
+    }
+
+    #[test]
+    fn blank_list_zeroes_values_in_place() {
+        let mut exif = parsed(ByteOrder::Big);
+        exif.scrub(&TagPolicy::from_lists(&[], &[], &[MAKE, DATE_TIME_ORIGINAL]));
+        let again = Exif::parse(&exif.to_payload()).expect("blanked block parses");
+        assert_eq!(again.ifd0.get(MAKE).map(|entry| entry.data.clone()), Some(vec![0; 6]));
+        let exif_ifd = again.ifd0.child(EXIF_IFD).expect("exif sub-ifd");
+        assert_eq!(exif_ifd.get(DATE_TIME_ORIGINAL).map(|entry| entry.data.clone()), Some(vec![0; 20]));
+    }
+
+    /// The sample with resolution tags in IFD0 and a colour space in the EXIF sub-IFD, written and parsed again.
+    fn with_resolution(order: ByteOrder) -> Exif {
+        let mut exif = parsed(order);
+        let short = |tag: u16, value: u16| {
+            let mut data = Vec::new();
+            order.put_u16(&mut data, value);
+            data.extend_from_slice(&[0, 0]);
+            Entry { tag, typ: SHORT, count: 1, data }
+        };
+        let mut rational = Vec::new();
+        order.put_u32(&mut rational, 300);
+        order.put_u32(&mut rational, 1);
+        exif.ifd0.entries.push(Entry { tag: X_RESOLUTION, typ: RATIONAL, count: 1, data: rational.clone() });
+        exif.ifd0.entries.push(Entry { tag: Y_RESOLUTION, typ: RATIONAL, count: 1, data: rational });
+        exif.ifd0.entries.push(short(RESOLUTION_UNIT, 2));
+        exif.ifd0.children.iter_mut()
+            .find(|(pointer, _)| *pointer == EXIF_IFD)
+            .expect("exif sub-ifd")
+            .1.entries.push(short(COLOR_SPACE, 1));
+        Exif::parse(&exif.to_payload()).expect("extended sample parses")
+    }
+
+    fn tags(ifd: &Ifd) -> Vec<u16> {
+        let mut tags: Vec<u16> = ifd.entries.iter().map(|entry| entry.tag).collect();
+        tags.sort();
+        tags
+    }
+
+    #[test]
+    fn display_only_keeps_orientation_and_nothing_else() {
+        for order in [ByteOrder::Little, ByteOrder::Big] {
+            let minimal = with_resolution(order).display_only(false).expect("orientation kept");
+            let again = Exif::parse(&minimal.to_payload()).expect("minimal block parses");
+            assert_eq!(tags(&again.ifd0), vec![ORIENTATION]);
+            assert_eq!(again.ifd0.get(ORIENTATION).map(|entry| entry.value_u32(order)), Some(6));
+            assert!(again.ifd0.children.is_empty());
+            assert!(again.ifd1.is_none() && again.thumbnail.is_none());
+        }
+    }
+
+    #[test]
+    fn display_only_keeps_resolution_when_asked() {
+        for order in [ByteOrder::Little, ByteOrder::Big] {
+            let minimal = with_resolution(order).display_only(true).expect("tags kept");
+            let again = Exif::parse(&minimal.to_payload()).expect("minimal block parses");
+            assert_eq!(tags(&again.ifd0), vec![ORIENTATION, X_RESOLUTION, Y_RESOLUTION, RESOLUTION_UNIT]);
+            assert_eq!(again.ifd0.children.len(), 1);
+            let exif_ifd = again.ifd0.child(EXIF_IFD).expect("exif sub-ifd");
+            assert_eq!(tags(exif_ifd), vec![COLOR_SPACE]);
+            assert!(again.ifd1.is_none() && again.thumbnail.is_none());
+        }
+    }
+
+    #[test]
+    fn display_only_without_display_tags_is_nothing() {
+        let mut exif = parsed(ByteOrder::Little);
+        exif.ifd0.entries.retain(|entry| entry.tag != ORIENTATION);
+        assert!(exif.display_only(true).is_none());
+    }
+}
@@ -0,0 +1,61 @@
+use crate::jpeg::exif::ByteOrder;
+
+/// Identifier that starts the payload of an MPF APP2 segment, followed by a TIFF header.
+pub(crate) const MPF_HEADER: &[u8; 4] = b"MPF\0";
+
+const MP_ENTRY: u16 = 0xb002;
+const UNDEFINED: u16 = 7;
+const MP_ENTRY_SIZE: usize = 16;
+
+/// One image listed in the MP index. `offset` counts from the TIFF header of the
+/// MPF segment of the first image, and is 0 for the first image itself.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct MpImage {
+    pub(crate) size: u32,
+    pub(crate) offset: u32,
+}
+
+/// The MP index of the first image of a multi-picture file: where the secondary images
+/// (depth maps, previews) after its EOI are, so they can be cleaned and moved.
+#[derive(Debug, Clone)]
+pub(crate) struct MpIndex {
+    order: ByteOrder,
+    /// Position of the first MP entry in the segment payload.
+    entries_at: usize,
+    pub(crate) images: Vec<MpImage>,
+}
+
+impl MpIndex {
+    /// Parses the MP index of an MPF APP2 payload, `MPF\0` header included.
+    /// Secondary images carry an MPF segment without an index, which gives `None`.
+    pub(crate) fn parse(payload: &[u8]) -> Option<MpIndex> {
+        let tiff = payload.strip_prefix(MPF_HEADER.as_slice())?;
+        let order = ByteOrder::of_tiff(tiff)?;
+        let ifd = order.u32(tiff.get(4..8)?) as usize;
+        let count = order.u16(tiff.get(ifd..ifd + 2)?) as usize;
+
+        for raw in tiff.get(ifd + 2..ifd + 2 + count * 12)?.chunks_exact(12) {
+            if order.u16(&raw[0..2]) != MP_ENTRY || order.u16(&raw[2..4]) != UNDEFINED {
+                continue
+            }
+            let size = order.u32(&raw[4..8]) as usize;
+            let at = order.u32(&raw[8..12]) as usize;
+            if !size.is_multiple_of(MP_ENTRY_SIZE) {
+                return None
+            }
+            let images = tiff.get(at..at + size)?
+                .chunks_exact(MP_ENTRY_SIZE)
+                .map(|entry| MpImage { size: order.u32(&entry[4..8]), offset: order.u32(&entry[8..12]) })
+                .collect();
+            return Some(MpIndex { order, entries_at: MPF_HEADER.len() + at, images })
+        }
+        None
+    }
+
+    /// Writes a new size and offset for image `n` into the segment `payload` the index came from.
+    pub(crate) fn patch(&self, payload: &mut [u8], n: usize, image: MpImage) {
+        let at = self.entries_at + n * MP_ENTRY_SIZE;
+        self.order.patch_u32(payload, at + 4, image.size);
+        self.order.patch_u32(payload, at + 8, image.offset);
+    }
+}
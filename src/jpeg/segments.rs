@@ -0,0 +1,213 @@
+use std::ops::Range;
+use crate::errors::error::{ExifStructureErr, PurgeErr};
+
+const SOI: u8 = 0xd8;
+const EOI: u8 = 0xd9;
+const SOS: u8 = 0xda;
+const TEM: u8 = 0x01;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum SegmentKind {
+    Soi,
+    Eoi,
+    App(u8),
+    Com,
+    Sof(u8),
+    Dht,
+    Dqt,
+    Dri,
+    Sos,
+    Other(u8),
+}
+
+impl SegmentKind {
+    fn from_marker(marker: u8) -> SegmentKind {
+        match marker {
+            SOI => SegmentKind::Soi,
+            EOI => SegmentKind::Eoi,
+            SOS => SegmentKind::Sos,
+            0xe0..=0xef => SegmentKind::App(marker - 0xe0),
+            0xfe => SegmentKind::Com,
+            0xc4 => SegmentKind::Dht,
+            0xdb => SegmentKind::Dqt,
+            0xdd => SegmentKind::Dri,
+            // C4, C8 and CC sit in the SOF range but are DHT, JPG and DAC.
+            0xc0..=0xcf if marker != 0xc8 && marker != 0xcc => SegmentKind::Sof(marker - 0xc0),
+            _ => SegmentKind::Other(marker),
+        }
+    }
+}
+
+/// One marker segment of a JPEG stream.
+///
+/// `range` spans the whole segment in the source buffer, marker bytes included.
+/// For SOS the range also covers the entropy-coded scan data (and any RST markers in it)
+/// up to the next real marker, so copying the range verbatim never touches pixel data.
+#[derive(Debug, Clone)]
+pub(crate) struct Segment {
+    pub(crate) kind: SegmentKind,
+    pub(crate) range: Range<usize>,
+    payload: Range<usize>,
+}
+
+impl Segment {
+    /// Segment payload without the marker and the length field.
+    pub(crate) fn payload<'a>(&self, src: &'a [u8]) -> &'a [u8] {
+        &src[self.payload.clone()]
+    }
+}
+
+/// Walks a JPEG marker by marker from SOI up to and including the first EOI.
+///
+/// Bytes after EOI are not part of any segment; `Segment::range.end` of the last
+/// segment tells the caller where they start.
+pub(crate) fn walk(src: &[u8]) -> Result<Vec<Segment>, PurgeErr> {
+    if src.get(..2) != Some(&[0xff, SOI]) {
+        return Err(PurgeErr::from(ExifStructureErr::new("not jpeg")))
+    }
+
+    let mut segments = vec![Segment {
+        kind: SegmentKind::Soi,
+        range: 0..2,
+        payload: 2..2,
+    }];
+    let mut pos = 2;
+
+    loop {
+        if src.get(pos) != Some(&0xff) {
+            return Err(PurgeErr::from(ExifStructureErr::new("jpeg marker expected but not found")))
+        }
+        // Any number of 0xFF fill bytes may precede a marker.
+        let start = pos;
+        while src.get(pos + 1) == Some(&0xff) {
+            pos += 1;
+        }
+        let marker = match src.get(pos + 1) {
+            Some(marker) => *marker,
+            None => return Err(PurgeErr::from(ExifStructureErr::new("truncated jpeg marker"))),
+        };
+        let kind = SegmentKind::from_marker(marker);
+
+        if marker == EOI {
+            segments.push(Segment { kind, range: start..pos + 2, payload: pos + 2..pos + 2 });
+            return Ok(segments)
+        }
+        if marker == TEM || (0xd0..=0xd7).contains(&marker) {
+            segments.push(Segment { kind, range: start..pos + 2, payload: pos + 2..pos + 2 });
+            pos += 2;
+            continue
+        }
+
+        let length = match src.get(pos + 2..pos + 4) {
+            Some(bytes) => u16::from_be_bytes([bytes[0], bytes[1]]) as usize,
+            None => return Err(PurgeErr::from(ExifStructureErr::new("truncated jpeg segment length"))),
+        };
+        if length < 2 {
+            return Err(PurgeErr::from(ExifStructureErr::new("invalid jpeg segment length")))
+        }
+        let end = pos + 2 + length;
+        if end > src.len() {
+            return Err(PurgeErr::from(ExifStructureErr::new("truncated jpeg segment")))
+        }
+        let payload = pos + 4..end;
+
+        if marker != SOS {
+            segments.push(Segment { kind, range: start..end, payload });
+            pos = end;
+            continue
+        }
+
+        let scan_end = skip_scan_data(src, end);
+        segments.push(Segment { kind, range: start..scan_end, payload });
+        if scan_end == src.len() {
+            // Scan data runs into the end of the file without EOI. Keep what is there.
+            return Ok(segments)
+        }
+        pos = scan_end;
+    }
+}
+
+/// Returns the offset of the first marker after entropy-coded data starting at `pos`.
+/// Stuffed zero bytes (FF 00) and restart markers (FF D0..D7) belong to the scan.
+fn skip_scan_data(src: &[u8], mut pos: usize) -> usize {
+    while pos + 1 < src.len() {
+        if src[pos] == 0xff {
+            match src[pos + 1] {
+                0x00 | 0xd0..=0xd7 => pos += 2,
+                0xff => pos += 1,
+                _ => return pos,
+            }
+        } else {
+            pos += 1;
+        }
+    }
+    src.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SOI, APP1, DQT, SOS with stuffed bytes and a restart marker in the scan, EOI, then two trailing bytes.
+    fn sample() -> Vec<u8> {
+        let mut jpeg = vec![0xff, SOI];
+        jpeg.extend_from_slice(&[0xff, 0xe1, 0x00, 0x06, b'E', b'x', b'i', b'f']);
+        // A fill byte before the marker.
+        jpeg.extend_from_slice(&[0xff, 0xff, 0xdb, 0x00, 0x03, 0x07]);
+        jpeg.extend_from_slice(&[0xff, SOS, 0x00, 0x02]);
+        jpeg.extend_from_slice(&[0x12, 0xff, 0x00, 0x34, 0xff, 0xd0, 0x56]);
+        jpeg.extend_from_slice(&[0xff, EOI]);
+        jpeg.extend_from_slice(&[0xaa, 0xbb]);
+        jpeg
+    }
+
+    #[test]
+    fn walks_segments_up_to_eoi() {
+        let jpeg = sample();
+        let segments = walk(&jpeg).expect("sample walks");
+        let kinds: Vec<SegmentKind> = segments.iter().map(|segment| segment.kind).collect();
+        assert_eq!(kinds, vec![SegmentKind::Soi, SegmentKind::App(1), SegmentKind::Dqt, SegmentKind::Sos, SegmentKind::Eoi]);
+
+        assert_eq!(segments[1].range, 2..10);
+        assert_eq!(segments[1].payload(&jpeg), b"Exif");
+        assert_eq!(segments[2].range, 10..16);
+        assert_eq!(segments[2].payload(&jpeg), &[0x07]);
+        // The scan runs over stuffed bytes and restart markers.
+        assert_eq!(segments[3].range, 16..27);
+        assert_eq!(segments[4].range, 27..29);
+        assert_eq!(&jpeg[segments[4].range.end..], &[0xaa, 0xbb]);
+    }
+
+    #[test]
+    fn sof_range_skips_dht_jpg_and_dac() {
+        assert_eq!(SegmentKind::from_marker(0xc0), SegmentKind::Sof(0));
+        assert_eq!(SegmentKind::from_marker(0xc2), SegmentKind::Sof(2));
+        assert_eq!(SegmentKind::from_marker(0xc4), SegmentKind::Dht);
+        assert_eq!(SegmentKind::from_marker(0xc8), SegmentKind::Other(0xc8));
+        assert_eq!(SegmentKind::from_marker(0xcc), SegmentKind::Other(0xcc));
+    }
+
+    #[test]
+    fn scan_without_eoi_ends_at_end_of_file() {
+        let mut jpeg = sample();
+        jpeg.truncate(27);
+        let segments = walk(&jpeg).expect("scan up to end of file walks");
+        assert_eq!(segments.last().map(|segment| (segment.kind, segment.range.clone())), Some((SegmentKind::Sos, 16..27)));
+    }
+
+    #[test]
+    fn truncated_segments_are_errors() {
+        let jpeg = sample();
+        // Cuts inside the header segments; the scan itself may legitimately run to the end of the file.
+        for cut in 0..16 {
+            assert!(walk(&jpeg[..cut]).is_err(), "cut at {}", cut);
+        }
+    }
+
+    #[test]
+    fn bad_lengths_and_missing_markers_are_errors() {
+        assert!(walk(&[0xff, SOI, 0xff, 0xe1, 0x00, 0x01]).is_err());
+        assert!(walk(&[0xff, SOI, 0x00, 0xe1, 0x00, 0x02]).is_err());
+        assert!(walk(b"\x89PNG").is_err());
+    }
+}
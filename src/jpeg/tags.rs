@@ -0,0 +1,90 @@
+use crate::jpeg::exif::GPS_IFD;
+
+const IMAGE_DESCRIPTION: u16 = 0x010e;
+const SOFTWARE: u16 = 0x0131;
+const ARTIST: u16 = 0x013b;
+const HOST_COMPUTER: u16 = 0x013c;
+const MAKER_NOTE: u16 = 0x927c;
+const USER_COMMENT: u16 = 0x9286;
+const XP_TITLE: u16 = 0x9c9b;
+const XP_COMMENT: u16 = 0x9c9c;
+const XP_AUTHOR: u16 = 0x9c9d;
+const XP_KEYWORDS: u16 = 0x9c9e;
+const XP_SUBJECT: u16 = 0x9c9f;
+const IMAGE_UNIQUE_ID: u16 = 0xa420;
+const CAMERA_OWNER_NAME: u16 = 0xa430;
+const BODY_SERIAL_NUMBER: u16 = 0xa431;
+const LENS_SERIAL_NUMBER: u16 = 0xa435;
+
+/// Tags that identify the photographer, the device or the place.
+/// MakerNote is on the list also because its vendor-specific internal offsets
+/// can't be relocated when the block is rewritten.
+const DEFAULT_DENY: [u16; 16] = [
+    IMAGE_DESCRIPTION, SOFTWARE, ARTIST, HOST_COMPUTER, MAKER_NOTE, USER_COMMENT,
+    XP_TITLE, XP_COMMENT, XP_AUTHOR, XP_KEYWORDS, XP_SUBJECT,
+    IMAGE_UNIQUE_ID, CAMERA_OWNER_NAME, BODY_SERIAL_NUMBER, LENS_SERIAL_NUMBER,
+    GPS_IFD,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum TagAction {
+    Keep,
+    Blank,
+    Drop,
+}
+
+/// Decides what happens to each EXIF tag when a block is scrubbed rather than deleted.
+///
+/// Rules apply to tags of IFD0 and the EXIF sub-IFD. Sub-IFD pointer tags (GPS, Interop)
+/// take rules and the fallback like any other tag and then act on the whole sub-IFD.
+/// Tags without a rule get `fallback`, which is `Keep` for a deny list and `Drop` for an allow list.
+#[derive(Debug, Clone)]
+pub(crate) struct TagPolicy {
+    rules: Vec<(u16, TagAction)>,
+    fallback: TagAction,
+}
+
+impl Default for TagPolicy {
+    fn default() -> Self {
+        TagPolicy {
+            rules: DEFAULT_DENY.iter().map(|tag| (*tag, TagAction::Drop)).collect(),
+            fallback: TagAction::Keep,
+        }
+    }
+}
+
+impl TagPolicy {
+    /// Builds the policy from command line lists. A non-empty `allow` list turns
+    /// the default deny list into an allow list; `deny` and `blank` are added on top.
+    pub(crate) fn from_lists(allow: &[u16], deny: &[u16], blank: &[u16]) -> TagPolicy {
+        let mut policy = if allow.is_empty() {
+            TagPolicy::default()
+        } else {
+            TagPolicy {
+                rules: allow.iter().map(|tag| (*tag, TagAction::Keep)).collect(),
+                fallback: TagAction::Drop,
+            }
+        };
+        policy.set(deny, TagAction::Drop);
+        policy.set(blank, TagAction::Blank);
+        policy
+    }
+
+    fn set(&mut self, tags: &[u16], action: TagAction) {
+        for tag in tags {
+            self.rules.retain(|(ruled, _)| ruled != tag);
+            self.rules.push((*tag, action));
+        }
+    }
+
+    /// Explicit rule for `tag`, if there is one.
+    pub(crate) fn rule(&self, tag: u16) -> Option<TagAction> {
+        self.rules.iter()
+            .find(|(ruled, _)| *ruled == tag)
+            .map(|(_, action)| *action)
+    }
+
+    pub(crate) fn action(&self, tag: u16) -> TagAction {
+        self.rule(tag).unwrap_or(self.fallback)
+    }
+}
@@ -0,0 +1,32 @@
+/// What was found appended after the final EOI of a JPEG.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum TrailerKind {
+    /// Samsung or Google motion photo video.
+    MotionPhoto,
+    /// Further JPEGs, usually MPF secondary images (depth maps, previews).
+    Jpeg,
+    /// A zip archive, i.e. a JPEG/zip polyglot.
+    Zip,
+    /// Zero or 0xFF filler some encoders leave behind.
+    Padding,
+    Unknown,
+}
+
+const SAMSUNG_MOTION_PHOTO: &[u8] = b"MotionPhoto_Data";
+
+impl TrailerKind {
+    pub(crate) fn classify(trailer: &[u8]) -> TrailerKind {
+        if trailer.get(4..8) == Some(b"ftyp")
+            || trailer.windows(SAMSUNG_MOTION_PHOTO.len()).any(|window| window == SAMSUNG_MOTION_PHOTO) {
+            TrailerKind::MotionPhoto
+        } else if trailer.starts_with(&[0xff, 0xd8, 0xff]) {
+            TrailerKind::Jpeg
+        } else if trailer.starts_with(b"PK\x03\x04") || trailer.windows(4).any(|window| window == b"PK\x05\x06") {
+            TrailerKind::Zip
+        } else if trailer.iter().all(|byte| *byte == 0x00) || trailer.iter().all(|byte| *byte == 0xff) {
+            TrailerKind::Padding
+        } else {
+            TrailerKind::Unknown
+        }
+    }
+}
@@ -6,6 +6,7 @@ mod traits;
 mod errors;
 mod png;
 mod jpeg;
+mod options;
 
 
 
@@ -19,13 +20,11 @@ use walkdir::{DirEntry, WalkDir};
 use std::sync::{Arc, Mutex, Condvar};
 use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
 use std::time::{Duration};
-use crate::mso_x::mso_x_core_xml_templates::*; // this doesn't seem to be used at all
-use regex::Regex;
 
-use lazy_static::lazy_static;
 use crate::errors::error::{PurgeErr, ToUser, UISideErr};
 
-use crate::traits::container::{DataPaths, Heaped, Purgable};
+use crate::traits::container::{DataPaths, Heaped, Purgable, UISideReport};
+use crate::options::{OPTIONS, USAGE};
 use native_dialog::{MessageDialog,};
 
 fn echo(name: &str) {
@@ -43,20 +42,6 @@ fn echo_succ() {
         .unwrap();
 }
 
-lazy_static! {
-    static ref RE1: Regex = Regex::new(r"<dc:title.*<dcterms:created").unwrap();
-}
-lazy_static! {
-    static ref RE2: Regex = Regex::new(r"<cp:category.*</cp:coreProperties>").unwrap();
-}
-lazy_static! {
-    static ref RE3: Regex = Regex::new(r#"<Relationship Id="rId4" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/custom-properties" Target="docProps/custom.xml"/>"#).unwrap();
-}
-
-
-const TARGET: &[u8] = br#"<Relationship Id="rId4" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/custom-properties" Target="docProps/custom.xml"/></Relationships>"#;
-const REPLACEMENT: &[u8; 16] = br#"</Relationships>"#;
-
 enum OutMessage {
     Data(Box<dyn Purgable>),
     ComputeEnd
@@ -67,28 +52,6 @@ enum InMessage {
     ComputeEnd,
 }
 
-fn replace_corexml(data: &str) -> String {
-
-    RE2.
-        replace_all(&RE1.replace_all(data, CoreXmlStr::TEXT_TEMPLATE_1), CoreXmlStr::TEXT_TEMPLATE_2)
-        .to_string()
-}
-
-
-fn find_rells(data: &Vec<u8>) -> Option<usize> {
-
-
-    data.windows(TARGET.len()).position(|subslice| subslice == TARGET)
-}
-
-fn remove_rells(mut data: Vec<u8>, index: usize) -> Vec<u8> {
-
-    data.truncate(index);
-    data.extend_from_slice(REPLACEMENT);
-    data
-
-}
-
 
 fn iterate_over_stubs(docs: Vec<Box<dyn Purgable>>,
                       itx: Sender<InMessage>,
@@ -96,8 +59,9 @@ fn iterate_over_stubs(docs: Vec<Box<dyn Purgable>>,
                       otx: Sender<OutMessage>,
                       orx: Receiver<OutMessage>,
                       lock: &Mutex<bool>,
-                      cvar: &Condvar) -> Vec<UISideErr> {
+                      cvar: &Condvar) -> (Vec<UISideErr>, Vec<UISideReport>) {
     let mut err_vec:Vec<UISideErr> = vec![];
+    let mut report_vec: Vec<UISideReport> = vec![];
     let mut started = lock.lock().unwrap();
 
     for stub in docs
@@ -176,8 +140,10 @@ fn iterate_over_stubs(docs: Vec<Box<dyn Purgable>>,
             match message {
                 OutMessage::Data(mut data) => {
                     let context = data.file_name();
-                   if let Err(err) =  data.save() {
-                       err_vec.push(err.to_user(context));
+                    let report = data.report();
+                   match data.save() {
+                       Ok(()) => report_vec.extend(report),
+                       Err(err) => err_vec.push(err.to_user(context)),
                    }
                 }
                 OutMessage::ComputeEnd => {
@@ -206,8 +172,10 @@ fn iterate_over_stubs(docs: Vec<Box<dyn Purgable>>,
             match message {
                 OutMessage::Data(mut data) => {
                     let context = data.file_name();
-                    if let Err(err) =  data.save() {
-                        err_vec.push(err.to_user(context));
+                    let report = data.report();
+                    match data.save() {
+                        Ok(()) => report_vec.extend(report),
+                        Err(err) => err_vec.push(err.to_user(context)),
                     }
                 }
                 OutMessage::ComputeEnd => {
@@ -217,15 +185,21 @@ fn iterate_over_stubs(docs: Vec<Box<dyn Purgable>>,
         }
     };
 
-err_vec
+(err_vec, report_vec)
 }
 
 fn main() -> () {
 
-    let path = env::args().nth(1).unwrap_or_else(|| {
-        println!("Usage: {} <directory>", env::args().next().unwrap());
-        std::process::exit(1);
-    });
+    let path = match (&OPTIONS.path, OPTIONS.unknown.is_empty()) {
+        (Some(path), true) => path.clone(),
+        _ => {
+            for flag in OPTIONS.unknown.iter() {
+                println!("Unknown option: {}", flag);
+            }
+            println!("Usage: {} {}", env::args().next().unwrap(), USAGE);
+            std::process::exit(1);
+        }
+    };
 
     let (oks, errs): (Vec<_>, Vec<_>) = WalkDir::new(path)
         .into_iter()
@@ -324,9 +298,15 @@ fn main() -> () {
     });
 
 
-    errs.extend(io_thread.join().unwrap());
+    let (io_errs, reports) = io_thread.join().unwrap();
+    errs.extend(io_errs);
     errs.extend(compute_thread.join().unwrap());
 
+    if !reports.is_empty() {
+        let reports = reports.into_iter().map(|item| item.ui_show()).collect::<Vec<String>>().join("\n");
+        echo(&reports);
+    }
+
     if errs.len() != 0 {
         let errs = errs.into_iter().map(|item| item.ui_show()).collect::<Vec<String>>().join("\n");
         echo(&errs);
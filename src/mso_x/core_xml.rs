@@ -0,0 +1,216 @@
+use quick_xml::events::{BytesEnd, Event};
+use quick_xml::name::{Namespace, ResolveResult};
+use quick_xml::reader::NsReader;
+use quick_xml::Writer;
+
+use crate::errors::error::PurgeErr;
+
+const DC: &[u8] = b"http://purl.org/dc/elements/1.1/";
+const DCTERMS: &[u8] = b"http://purl.org/dc/terms/";
+const CP: &[u8] = b"http://schemas.openxmlformats.org/package/2006/metadata/core-properties";
+
+/// Core properties holding a date. An empty date isn't a valid W3CDTF value, so these are removed even when blanked.
+const DATES: [&str; 3] = ["created", "modified", "lastPrinted"];
+
+/// The revision number says how often the document was saved; an empty one isn't a number either.
+const REVISION: &str = "revision";
+
+/// What happens to one element of `docProps/core.xml`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum PropertyAction {
+    Keep,
+    /// Keep the element and its attributes, drop its content.
+    Blank,
+    Remove,
+}
+
+/// Decides what happens to each Dublin Core, dcterms and core-properties element.
+/// Anything in another namespace is left alone.
+///
+/// Properties are named by local name (`creator`, `revision`), a prefix like `cp:` is ignored.
+/// Properties without a rule get `fallback`: by default everything is blanked, and dates and the
+/// revision number are removed.
+#[derive(Debug, Clone)]
+pub(crate) struct CorePolicy {
+    rules: Vec<(String, PropertyAction)>,
+    fallback: PropertyAction,
+}
+
+impl Default for CorePolicy {
+    fn default() -> Self {
+        CorePolicy {
+            rules: DATES.iter().chain([REVISION].iter())
+                .map(|name| (name.to_string(), PropertyAction::Remove))
+                .collect(),
+            fallback: PropertyAction::Blank,
+        }
+    }
+}
+
+impl CorePolicy {
+    /// Builds the policy from command line lists. With `keep_unlisted`, properties
+    /// that aren't on any list are kept instead of blanked.
+    pub(crate) fn from_lists(keep: &[String], blank: &[String], remove: &[String], keep_unlisted: bool) -> CorePolicy {
+        let mut policy = CorePolicy::default();
+        if keep_unlisted {
+            policy.rules.clear();
+            policy.fallback = PropertyAction::Keep;
+        }
+        policy.set(keep, PropertyAction::Keep);
+        policy.set(blank, PropertyAction::Blank);
+        policy.set(remove, PropertyAction::Remove);
+        policy
+    }
+
+    fn set(&mut self, names: &[String], action: PropertyAction) {
+        for name in names {
+            let name = name.rsplit(':').next().unwrap_or(name);
+            self.rules.retain(|(ruled, _)| ruled != name);
+            self.rules.push((name.to_string(), action));
+        }
+    }
+
+    fn action(&self, namespace: &ResolveResult, local_name: &[u8]) -> PropertyAction {
+        match namespace {
+            ResolveResult::Bound(Namespace(ns)) if [DC, DCTERMS, CP].contains(ns) => {
+                let action = self.rules.iter()
+                    .find(|(ruled, _)| ruled.as_bytes() == local_name)
+                    .map(|(_, action)| *action)
+                    .unwrap_or(self.fallback);
+                let not_blankable = DATES.iter().chain([REVISION].iter()).any(|name| name.as_bytes() == local_name);
+                if action == PropertyAction::Blank && not_blankable {
+                    PropertyAction::Remove
+                } else {
+                    action
+                }
+            },
+            _ => PropertyAction::Keep,
+        }
+    }
+}
+
+/// A property being blanked or removed: its depth, its name, and whether there was anything in it.
+struct Skipped {
+    depth: usize,
+    name: String,
+    had_value: bool,
+}
+
+/// Rewrites `docProps/core.xml` with its properties blanked or removed as `policy` says, whatever their order and formatting.
+/// Returns the new document and the names of the properties that had a value.
+pub(crate) fn clean(xml: &[u8], policy: &CorePolicy) -> Result<(Vec<u8>, Vec<String>), PurgeErr> {
+    let mut reader = NsReader::from_reader(xml);
+    let mut writer = Writer::new(Vec::with_capacity(xml.len()));
+    let mut cleaned: Vec<String> = Vec::new();
+
+    // 1 inside the root element, 2 inside a property.
+    let mut depth = 0usize;
+    let mut skipping: Option<Skipped> = None;
+
+    loop {
+        let (namespace, event) = reader.read_resolved_event()?;
+        if let Some(skipped) = skipping.as_mut() {
+            match &event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) if depth == skipped.depth => {
+                    depth -= 1;
+                    if skipped.had_value {
+                        cleaned.push(std::mem::take(&mut skipped.name));
+                    }
+                    skipping = None;
+                    continue
+                },
+                Event::End(_) => depth -= 1,
+                Event::Eof => break,
+                _ => {},
+            }
+            skipped.had_value |= match &event {
+                Event::Text(text) => !text.iter().all(u8::is_ascii_whitespace),
+                Event::Comment(_) => false,
+                _ => true,
+            };
+            continue
+        }
+
+        match &event {
+            Event::Eof => break,
+            Event::Start(start) => {
+                depth += 1;
+                let action = if depth == 2 { policy.action(&namespace, start.local_name().as_ref()) } else { PropertyAction::Keep };
+                if action != PropertyAction::Keep {
+                    let name = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+                    if action == PropertyAction::Blank {
+                        writer.write_event(Event::Start(start.clone()))?;
+                        writer.write_event(Event::End(BytesEnd::new(name.as_str())))?;
+                    }
+                    skipping = Some(Skipped { depth, name, had_value: false });
+                    continue
+                }
+            },
+            Event::End(_) => depth -= 1,
+            // Nothing to blank in an empty property, but a removed one goes either way.
+            Event::Empty(empty) if depth == 1 && policy.action(&namespace, empty.local_name().as_ref()) == PropertyAction::Remove => continue,
+            _ => {},
+        }
+        writer.write_event(event)?;
+    }
+
+    Ok((writer.into_inner(), cleaned))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:x="urn:example"><cp:revision>7</cp:revision><dc:creator>Jane Doe</dc:creator>
+  <dc:title
+    lang="en">Plans</dc:title><dc:language>en-GB</dc:language><cp:lastModifiedBy/><x:note>kept</x:note><dcterms:created xsi:type="dcterms:W3CDTF">2020-01-01T00:00:00Z</dcterms:created></cp:coreProperties>"#;
+
+    fn cleaned(policy: &CorePolicy) -> (String, Vec<String>) {
+        let (xml, names) = clean(CORE.as_bytes(), policy).expect("cleans");
+        (String::from_utf8(xml).expect("utf-8"), names)
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn blanks_properties_and_removes_dates_and_revision_by_default() {
+        let (xml, cleaned) = cleaned(&CorePolicy::default());
+        assert!(!xml.contains("revision") && !xml.contains("created"));
+        assert!(xml.contains("<dc:creator></dc:creator>") && xml.contains("<dc:language></dc:language>"));
+        assert!(xml.contains("<dc:title\n    lang=\"en\"></dc:title>"));
+        assert!(xml.contains("<cp:lastModifiedBy/>") && xml.contains("<x:note>kept</x:note>"));
+        assert_eq!(cleaned, names(&["cp:revision", "dc:creator", "dc:title", "dc:language", "dcterms:created"]));
+    }
+
+    #[test]
+    fn lists_override_the_defaults() {
+        let policy = CorePolicy::from_lists(&names(&["language", "cp:revision"]), &names(&["created"]), &names(&["creator"]), false);
+        let (xml, cleaned) = cleaned(&policy);
+        assert!(xml.contains("<dc:language>en-GB</dc:language>") && xml.contains("<cp:revision>7</cp:revision>"));
+        assert!(!xml.contains("creator") && !xml.contains("created"));
+        assert!(xml.contains("<dc:title\n    lang=\"en\"></dc:title>"));
+        assert_eq!(cleaned, names(&["dc:creator", "dc:title", "dcterms:created"]));
+    }
+
+    #[test]
+    fn unlisted_properties_can_be_kept() {
+        let policy = CorePolicy::from_lists(&[], &names(&["title"]), &names(&["revision", "lastModifiedBy"]), true);
+        let (xml, cleaned) = cleaned(&policy);
+        assert!(!xml.contains("revision") && !xml.contains("lastModifiedBy"));
+        assert!(xml.contains("<dc:creator>Jane Doe</dc:creator>") && xml.contains("2020-01-01T00:00:00Z"));
+        assert_eq!(cleaned, names(&["cp:revision", "dc:title"]));
+    }
+
+    #[test]
+    fn the_revision_is_removed_when_listed_for_blanking() {
+        let policy = CorePolicy::from_lists(&[], &names(&["revision"]), &[], true);
+        let (xml, cleaned) = cleaned(&policy);
+        assert!(!xml.contains("revision"));
+        assert!(xml.contains("<dc:creator>Jane Doe</dc:creator>"));
+        assert_eq!(cleaned, names(&["cp:revision"]));
+    }
+}
@@ -1,4 +1,5 @@
 pub mod mso_x;
 mod utils;
-pub mod mso_x_core_xml_templates;
+mod core_xml;
+mod package;
 pub mod mso_x_file_name_consts;
\ No newline at end of file
@@ -6,12 +6,15 @@ use std::io::{Read, Write};
 use crate::errors::error::PurgeErr;
 
 use zip::{ZipArchive, ZipWriter};
+use zip::result::ZipError;
 use zip::write::FileOptions;
-use crate::{find_rells, remove_rells, replace_corexml};
+use crate::mso_x::{core_xml, package};
+use crate::mso_x::core_xml::CorePolicy;
 use crate::mso_x::mso_x_file_name_consts;
 
 
 use lazy_static::lazy_static;
+use crate::options::OPTIONS;
 use crate::traits::container::{DataPaths, Heaped};
 
 
@@ -26,7 +29,9 @@ enum rw_MsOX {
 }
 pub(crate) struct MsOX {
     paths: DataPaths,
-    data: rw_MsOX
+    data: rw_MsOX,
+    core: CorePolicy,
+    report: Vec<String>
 }
 
 
@@ -36,6 +41,8 @@ impl Heaped for  MsOX {
             MsOX {
                 paths,
                 data: rw_MsOX::Stub,
+                core: CorePolicy::from_lists(&OPTIONS.core_keep, &OPTIONS.core_blank, &OPTIONS.core_drop, OPTIONS.core_keep_unlisted),
+                report: Vec::new(),
             }
         )
     }
@@ -59,6 +66,17 @@ impl Heaped for  MsOX {
             rw_MsOX::Archive(archive) => {archive}
             rw_MsOX::Writer(_) => {unreachable!("It can't happen.")}
         };
+        // The relationships are needed before the parts they point at, whatever the order in the archive.
+        let (rels, custom_parts) = match archive.by_name(mso_x_file_name_consts::RELS_XML) {
+            Ok(mut file) => {
+                let mut content = Vec::with_capacity(1024);
+                file.read_to_end(&mut content)?;
+                let (rels, parts) = package::remove_custom_properties(&content)?;
+                (Some(rels), parts)
+            },
+            Err(ZipError::FileNotFound) => (None, Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
         for i in 0..archive.len() {
             let mut file = archive.by_index(i)?;
             let outpath = match file.enclosed_name() {
@@ -73,24 +91,27 @@ impl Heaped for  MsOX {
             match outpath.as_str() {
                 to_edit @ mso_x_file_name_consts::CORE_XML => {
                     file.by_ref().read_to_end(&mut content)?;
-                    let corexml = std::str::from_utf8(&content)?;
-                    let replxml = replace_corexml(corexml);
+                    let (corexml, cleaned) = core_xml::clean(&content, &self.core)?;
+                    if !cleaned.is_empty() {
+                        self.report.push(format!("cleaned document properties: {}", cleaned.join(", ")));
+                    }
                     zipout.start_file(to_edit, *DEFLATE_OPTION)?;
-                    zipout.write_all(replxml.as_bytes())?;
+                    zipout.write_all(&corexml)?;
                 }
                 to_edit @ mso_x_file_name_consts::RELS_XML => {
-                    file.read_to_end(&mut content);
-                    if let Some(index) = find_rells(&content) {
-                        // println!("{}", index);
-                        let rels = remove_rells(content, index);
-                        zipout.start_file(to_edit, *DEFLATE_OPTION)?;
-                        zipout.write_all(&rels);
-                    } else {
-                        zipout.start_file(to_edit, *DEFLATE_OPTION)?;
-                        zipout.write_all(content.as_slice())?;
-                    }
+                    zipout.start_file(to_edit, *DEFLATE_OPTION)?;
+                    zipout.write_all(rels.as_deref().unwrap_or_default())?;
+                }
+                to_edit @ mso_x_file_name_consts::CONTENT_TYPES_XML => {
+                    file.read_to_end(&mut content)?;
+                    let content_types = package::remove_overrides(&content, &custom_parts)?;
+                    zipout.start_file(to_edit, *DEFLATE_OPTION)?;
+                    zipout.write_all(&content_types)?;
+                }
+                removed if package::is_removed_part(removed, &custom_parts) => {
+                    self.report.push(format!("removed custom properties: {}", removed));
+                    continue
                 }
-                mso_x_file_name_consts::CUSTOM_XML => continue,
 
                 no_edit => {
                     // file.read_to_end(&mut content).unwrap();
@@ -106,6 +127,10 @@ impl Heaped for  MsOX {
         Ok(())
     }
 
+    fn report(&self) -> Vec<String> {
+        self.report.clone()
+    }
+
     fn save(&mut self) -> Result<(), PurgeErr>{
         let mut archive = match &mut self.data {
             rw_MsOX::Stub => {unreachable!("Can't happen.")},
@@ -1,3 +1,4 @@
 pub const CORE_XML: &str = "docProps/core.xml";
 pub const RELS_XML: &str = "_rels/.rels";
-pub const CUSTOM_XML: &str = "docProps/custom.xml";
\ No newline at end of file
+pub const CUSTOM_XML: &str = "docProps/custom.xml";
+pub const CONTENT_TYPES_XML: &str = "[Content_Types].xml";
\ No newline at end of file
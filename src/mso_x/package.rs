@@ -0,0 +1,120 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use quick_xml::Writer;
+
+use crate::errors::error::PurgeErr;
+use crate::mso_x::mso_x_file_name_consts;
+
+const CUSTOM_PROPERTIES_TYPE: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/custom-properties";
+const CUSTOM_PROPERTIES_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.custom-properties+xml";
+
+fn attribute(start: &BytesStart, name: &[u8]) -> Result<Option<String>, quick_xml::Error> {
+    match start.try_get_attribute(name)? {
+        Some(attr) => Ok(Some(attr.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+/// Copies `xml`, leaving out every element named `local_name` that `drop` picks, along with its content.
+fn drop_elements<F>(xml: &[u8], local_name: &[u8], mut drop: F) -> Result<Vec<u8>, PurgeErr>
+    where F: FnMut(&BytesStart) -> Result<bool, quick_xml::Error>
+{
+    let mut reader = Reader::from_reader(xml);
+    let mut writer = Writer::new(Vec::with_capacity(xml.len()));
+
+    loop {
+        let event = reader.read_event()?;
+        match &event {
+            Event::Eof => break,
+            Event::Empty(start) if start.local_name().as_ref() == local_name && drop(start)? => continue,
+            Event::Start(start) if start.local_name().as_ref() == local_name && drop(start)? => {
+                let end = start.to_end().into_owned();
+                reader.read_to_end(end.name())?;
+                continue
+            },
+            _ => {},
+        }
+        writer.write_event(event)?;
+    }
+
+    Ok(writer.into_inner())
+}
+
+/// Part names are compared without the leading slash and ignoring ASCII case, as OPC does.
+fn part_name(name: &str) -> String {
+    name.trim_start_matches('/').to_ascii_lowercase()
+}
+
+/// Removes every custom-properties relationship from `_rels/.rels`, whatever its Id and position.
+/// Returns the new document and the parts those relationships pointed at.
+pub(crate) fn remove_custom_properties(rels: &[u8]) -> Result<(Vec<u8>, Vec<String>), PurgeErr> {
+    let mut parts: Vec<String> = Vec::new();
+    let rels = drop_elements(rels, b"Relationship", |relationship| {
+        if attribute(relationship, b"Type")?.as_deref() != Some(CUSTOM_PROPERTIES_TYPE) {
+            return Ok(false)
+        }
+        let external = attribute(relationship, b"TargetMode")?.as_deref() == Some("External");
+        if let Some(target) = attribute(relationship, b"Target")?.filter(|_| !external) {
+            let target = part_name(&target);
+            if !parts.contains(&target) {
+                parts.push(target);
+            }
+        }
+        Ok(true)
+    })?;
+    Ok((rels, parts))
+}
+
+/// The relationships of `part` live in `<dir>/_rels/<name>.rels`.
+fn rels_of(part: &str) -> String {
+    match part.rsplit_once('/') {
+        Some((dir, name)) => format!("{}/_rels/{}.rels", dir, name),
+        None => format!("_rels/{}.rels", part),
+    }
+}
+
+/// Whether `name`, a file in the archive, is one of the custom properties `parts` or `docProps/custom.xml`,
+/// or the relationships of one, which would be left without their source.
+pub(crate) fn is_removed_part(name: &str, parts: &[String]) -> bool {
+    let name = part_name(name);
+    parts.iter()
+        .map(String::as_str)
+        .chain([part_name(mso_x_file_name_consts::CUSTOM_XML).as_str()])
+        .any(|part| name == part || name == rels_of(part))
+}
+
+/// Removes the `[Content_Types].xml` overrides of the removed `parts` and any left for custom properties.
+pub(crate) fn remove_overrides(content_types: &[u8], parts: &[String]) -> Result<Vec<u8>, PurgeErr> {
+    drop_elements(content_types, b"Override", |over| {
+        let removed_part = attribute(over, b"PartName")?.map(|name| is_removed_part(&name, parts)).unwrap_or(false);
+        Ok(removed_part || attribute(over, b"ContentType")?.as_deref() == Some(CUSTOM_PROPERTIES_CONTENT_TYPE))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties" Target="docProps/app.xml"/><Relationship Id="rId9" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/custom-properties" Target="/docProps/Custom2.xml"/><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/></Relationships>"#;
+
+    #[test]
+    fn removes_custom_properties_relationships_by_type() {
+        let (rels, parts) = remove_custom_properties(RELS.as_bytes()).expect("cleans");
+        let rels = String::from_utf8(rels).expect("utf-8");
+        assert!(!rels.contains("custom-properties") && rels.contains("rId3") && rels.contains("rId1"));
+        assert_eq!(parts, vec!["docprops/custom2.xml".to_string()]);
+    }
+
+    #[test]
+    fn removed_parts_take_their_relationships_along() {
+        let parts = vec!["docprops/custom2.xml".to_string()];
+        assert!(is_removed_part("docProps/Custom2.xml", &parts));
+        assert!(is_removed_part("docProps/_rels/custom2.xml.rels", &parts));
+        assert!(is_removed_part("docProps/custom.xml", &parts));
+        assert!(is_removed_part("docProps/_rels/custom.xml.rels", &parts));
+        assert!(!is_removed_part("docProps/_rels/app.xml.rels", &parts));
+        assert!(!is_removed_part("_rels/.rels", &parts));
+        assert!(!is_removed_part("word/_rels/document.xml.rels", &parts));
+    }
+}
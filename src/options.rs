@@ -0,0 +1,170 @@
+use std::env;
+use lazy_static::lazy_static;
+use crate::pdf::id::IdMode;
+
+lazy_static! {
+    pub(crate) static ref OPTIONS: Options = Options::from_args(env::args().skip(1));
+}
+
+pub(crate) const USAGE: &str = "[options] <directory | file>
+
+Options:
+    --keep-orientation    keep the EXIF Orientation tag of JPEGs in a minimal EXIF block
+    --keep-resolution     with --keep-orientation, also keep resolution and colour space tags
+    --scrub-exif          remove identifying EXIF tags (GPS, serial numbers, owner, MakerNote...)
+                          instead of the whole EXIF block
+    --exif-allow=<tags>   with --scrub-exif, keep only these tags
+    --exif-deny=<tags>    with --scrub-exif, also remove these tags
+    --exif-blank=<tags>   with --scrub-exif, zero out the values of these tags
+    --keep-trailer        keep data appended after the end of a JPEG (motion photo videos,
+                          MPF images). MPF images are cleaned like the main image
+    --png-keep=<chunks>   keep these PNG chunk types, e.g. tIME,bKGD
+    --png-drop=<chunks>   remove these PNG chunk types, e.g. iCCP
+    --rewrite-xmp         clean PDF XMP metadata in place instead of deleting the stream,
+                          keeping PDF/A identification
+    --scrub-authorship    remove author names and dates from PDF annotations and outline items,
+                          along with comment popups and replies
+    --annotation-author=<name>
+                          with --scrub-authorship, put this name on annotations instead
+    --remove-markup       remove all PDF markup annotations (comments, highlights, stamps...)
+    --harden-pdf          remove embedded files, attachments, actions run on opening, and
+                          JavaScript, Launch, SubmitForm and ImportData actions from PDFs
+    --clear-forms         remove the values of PDF form fields, leaving a blank form
+    --remove-xfa          remove the XFA datasets of PDF forms
+    --flatten-forms       draw PDF form fields into the page and remove the form
+    --core-keep=<names>   keep these Office core properties, e.g. title,language
+    --core-blank=<names>  empty these Office core properties, keeping the element
+    --core-drop=<names>   remove these Office core properties, e.g. revision
+    --core-keep-unlisted  keep Office core properties that aren't on a list instead of
+                          blanking them and removing dates and the revision number
+    --pdf-password=<password>
+                          open encrypted PDFs with this password; they are encrypted again
+                          with the same permissions
+    --linearize-pdf       linearize PDFs that were linearized (fast web view) again with qpdf,
+                          which has to be installed; goes with --pdf-id=keep or hash only
+    --pdf-id=<mode>       what to do with the PDF trailer ID: keep (default), remove, random,
+                          or hash for an ID derived from the cleaned content
+
+Tags are comma separated numbers, decimal or 0x-prefixed hex (e.g. 0x8825 for the GPS IFD).";
+
+/// Command line switches. Everything that isn't a switch is taken as the path to purge.
+#[derive(Debug, Default)]
+pub(crate) struct Options {
+    pub(crate) path: Option<String>,
+    pub(crate) unknown: Vec<String>,
+
+    pub(crate) keep_orientation: bool,
+    pub(crate) keep_resolution: bool,
+    pub(crate) scrub_exif: bool,
+    pub(crate) exif_allow: Vec<u16>,
+    pub(crate) exif_deny: Vec<u16>,
+    pub(crate) exif_blank: Vec<u16>,
+    pub(crate) keep_trailer: bool,
+    pub(crate) png_keep: Vec<[u8; 4]>,
+    pub(crate) png_drop: Vec<[u8; 4]>,
+    pub(crate) core_keep: Vec<String>,
+    pub(crate) core_blank: Vec<String>,
+    pub(crate) core_drop: Vec<String>,
+    pub(crate) core_keep_unlisted: bool,
+    pub(crate) rewrite_xmp: bool,
+    pub(crate) scrub_authorship: bool,
+    pub(crate) annotation_author: Option<String>,
+    pub(crate) remove_markup: bool,
+    pub(crate) harden_pdf: bool,
+    pub(crate) clear_forms: bool,
+    pub(crate) remove_xfa: bool,
+    pub(crate) flatten_forms: bool,
+    pub(crate) pdf_id: IdMode,
+    pub(crate) pdf_password: Option<String>,
+    pub(crate) linearize_pdf: bool,
+}
+
+fn parse_tags(list: &str) -> Option<Vec<u16>> {
+    list.split(',')
+        .map(|tag| match tag.trim().strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16).ok(),
+            None => tag.trim().parse().ok(),
+        })
+        .collect()
+}
+
+fn parse_names(list: &str) -> Option<Vec<String>> {
+    list.split(',')
+        .map(|name| Some(name.trim()).filter(|name| !name.is_empty()).map(str::to_string))
+        .collect()
+}
+
+fn parse_chunk_types(list: &str) -> Option<Vec<[u8; 4]>> {
+    list.split(',')
+        .map(|kind| <[u8; 4]>::try_from(kind.trim().as_bytes()).ok()
+            .filter(|kind| kind.iter().all(u8::is_ascii_alphabetic)))
+        .collect()
+}
+
+impl Options {
+    fn from_args<I: Iterator<Item = String>>(args: I) -> Options {
+        let mut options = Options::default();
+        for arg in args {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            match (flag, value) {
+                ("--keep-orientation", None) => options.keep_orientation = true,
+                ("--keep-resolution", None) => options.keep_resolution = true,
+                ("--scrub-exif", None) => options.scrub_exif = true,
+                ("--keep-trailer", None) => options.keep_trailer = true,
+                ("--rewrite-xmp", None) => options.rewrite_xmp = true,
+                ("--scrub-authorship", None) => options.scrub_authorship = true,
+                ("--harden-pdf", None) => options.harden_pdf = true,
+                ("--clear-forms", None) => options.clear_forms = true,
+                ("--remove-xfa", None) => options.remove_xfa = true,
+                ("--flatten-forms", None) => options.flatten_forms = true,
+                ("--remove-markup", None) => options.remove_markup = true,
+                ("--linearize-pdf", None) => options.linearize_pdf = true,
+                ("--core-keep-unlisted", None) => options.core_keep_unlisted = true,
+                ("--pdf-password", Some(password)) => options.pdf_password = Some(password.to_string()),
+                ("--annotation-author", Some(name)) => options.annotation_author = Some(name.to_string()),
+                ("--png-keep", Some(list)) => match parse_chunk_types(list) {
+                    Some(kinds) => options.png_keep.extend(kinds),
+                    None => options.unknown.push(arg),
+                },
+                ("--png-drop", Some(list)) => match parse_chunk_types(list) {
+                    Some(kinds) => options.png_drop.extend(kinds),
+                    None => options.unknown.push(arg),
+                },
+                ("--core-keep", Some(list)) => match parse_names(list) {
+                    Some(names) => options.core_keep.extend(names),
+                    None => options.unknown.push(arg),
+                },
+                ("--core-blank", Some(list)) => match parse_names(list) {
+                    Some(names) => options.core_blank.extend(names),
+                    None => options.unknown.push(arg),
+                },
+                ("--core-drop", Some(list)) => match parse_names(list) {
+                    Some(names) => options.core_drop.extend(names),
+                    None => options.unknown.push(arg),
+                },
+                ("--pdf-id", Some(mode)) => match IdMode::parse(mode) {
+                    Some(mode) => options.pdf_id = mode,
+                    None => options.unknown.push(arg),
+                },
+                ("--exif-allow", Some(list)) => match parse_tags(list) {
+                    Some(tags) => options.exif_allow.extend(tags),
+                    None => options.unknown.push(arg),
+                },
+                ("--exif-deny", Some(list)) => match parse_tags(list) {
+                    Some(tags) => options.exif_deny.extend(tags),
+                    None => options.unknown.push(arg),
+                },
+                ("--exif-blank", Some(list)) => match parse_tags(list) {
+                    Some(tags) => options.exif_blank.extend(tags),
+                    None => options.unknown.push(arg),
+                },
+                (flag, _) if flag.starts_with("--") => options.unknown.push(arg),
+                _ => options.path = Some(arg),
+            }
+        }
+        options
+    }
+}
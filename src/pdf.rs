@@ -1,26 +1,53 @@
 #![deny(clippy::unwrap_used)]
 
+mod refs;
+mod annotations;
+mod outlines;
+pub(crate) mod id;
+mod revisions;
+mod hardening;
+mod images;
+mod private;
+mod encryption;
+mod forms;
+mod objects;
+mod compression;
+mod linearize;
+#[cfg(test)]
+mod test_support;
+
 use std::fmt::{Debug, Pointer};
+use std::fs::File;
+use std::io::Write;
 
-use lopdf::{Document, ObjectId, Stream};
+use lopdf::{Dictionary, Object, ObjectId, SaveOptions, Stream};
 
-use std::str::{from_utf8};
-use xmp_toolkit::{XmpMeta, XmpProperty};
-use crate::errors::error::PurgeErr;
+use std::str::{from_utf8, FromStr};
+use xmp_toolkit::{IterOptions, ToStringOptions, XmpMeta, XmpProperty};
+use crate::errors::error::{PdfStructureErr, PurgeErr};
+use crate::jpeg::Cleaner;
+use crate::options::OPTIONS;
 
 use crate::traits::container::{DataPaths, Heaped, Purgable};
 
-const ELEMENTS_TO_CLEAN: [(&str, &str); 4] = [
-    ("http://purl.org/dc/elements/1.1/", "dc:title[1]" ),
-    ("http://purl.org/dc/elements/1.1/", "dc:creator", ),
-    ("http://ns.adobe.com/pdf/1.3/", "pdf:Producer"),
-    ("http://ns.adobe.com/xap/1.0/mm/", "xmp:CreatorTool"),
+// PDF/A and PDF/UA identification (pdfaid:part, pdfaid:conformance, pdfuaid:part).
+// Validators reject the file without them, and they say nothing about the author.
+const XMP_KEPT_NAMESPACES: [&str; 2] = [
+    "http://www.aiim.org/pdfa/ns/id/",
+    "http://www.aiim.org/pdfua/ns/id/",
 ];
 
 const PDF_METADATA_KEYS: [&str; 11] = [
 "Title", "Author", "Subject", "Keywords", "Creator", "Producer", "CreationDate", "ModDate", "Comments", "Company", "SourceModified"
 ];
 
+pub(crate) fn metadata_keys(dict: &Dictionary) -> Vec<Vec<u8>> {
+    dict.iter()
+        .filter(|(key, _)| from_utf8(key).map(|key| PDF_METADATA_KEYS.contains(&key)).unwrap_or(false))
+        .map(|(key, _)| key.clone())
+        .collect()
+}
+
 const XMP_META_STREAM_KEYS: [&str; 2] = ["Subtype", "Type"];
 const XMP_META_STREAM_SUBKEYS: [&str; 2] = ["XML", "Metadata"];
 fn is_xmp_meta_stream(strm: &Stream ) -> Option<bool> {
@@ -28,8 +55,8 @@ fn is_xmp_meta_stream(strm: &Stream ) -> Option<bool> {
     Option::from(
         XMP_META_STREAM_KEYS.iter()
         .filter_map(    |key|
-            strm.dict.get(key.as_bytes()).ok()?
-            .as_name_str().ok()
+            from_utf8(strm.dict.get(key.as_bytes()).ok()?
+            .as_name().ok()?).ok()
         )
         .collect::<Vec<&str>>()
         .eq(&XMP_META_STREAM_SUBKEYS)
@@ -37,33 +64,27 @@ fn is_xmp_meta_stream(strm: &Stream ) -> Option<bool> {
 
 }
 fn clean_xmp(mut xmp: XmpMeta) -> Result<XmpMeta, PurgeErr>{
-    // for (ns, name) in ELEMENTS_TO_CLEAN {
-    //     if let Some(_) = xmp.property(ns,name) {
-    //         xmp.set_property(ns, name, &XmpValue::new("".to_owned()))?
-    //     }
-    // }
-    let mut properties: Vec<XmpProperty> = Vec::new();
-    for property in xmp.iter(Default::default()){
-        if &property.name != "" {
-            println!("For property '{}' schema is {}.", &property.name, &property.schema_ns);
-            properties.push(property.clone())
-        }
-    };
+    // Only top level properties; deleting those takes their array items, struct fields and qualifiers along.
+    let properties: Vec<XmpProperty> = xmp.iter(IterOptions::default())
+        .filter(|property| !property.name.is_empty() && !property.name.contains(['/', '[']))
+        .filter(|property| !XMP_KEPT_NAMESPACES.contains(&property.schema_ns.as_str()))
+        .collect();
 
     for prop in properties.iter() {
-        xmp.delete_property(&prop.schema_ns, &prop.name).unwrap()
-    };
-
-    println!("_________________________\n\n");
-    for property in xmp.iter(Default::default()){
-        if &property.name != "" {
-            println!("For property '{}' schema is {}.", &property.name, &property.schema_ns);
-            properties.push(property.clone())
-        }
+        xmp.delete_property(&prop.schema_ns, &prop.name)?
     };
     Ok(xmp)
 }
 
+/// Replaces the XMP packet of a metadata stream with a cleaned one.
+fn rewrite_xmp_stream(strm: &mut Stream) -> Result<(), PurgeErr> {
+    let content = strm.decompressed_content().unwrap_or_else(|_| strm.content.clone());
+    let loaded = XmpMeta::from_str(from_utf8(&content)?)?;
+    let cleaned_xmp = clean_xmp(loaded)?.to_string_with_options(ToStringOptions::default())?;
+    strm.set_plain_content(cleaned_xmp.into_bytes());
+    Ok(())
+}
+
 enum state_Doc {
     Stub,
     Data(lopdf::Document)
@@ -76,7 +97,20 @@ enum dirty_Objs {
 }
 pub(crate) struct Pdf {
     paths: DataPaths,
-    data: state_Doc
+    data: state_Doc,
+    rewrite_xmp: bool,
+    annotations: annotations::AnnotationPolicy,
+    harden: bool,
+    forms: forms::FormPolicy,
+    images: Cleaner,
+    id_mode: id::IdMode,
+    password: Option<String>,
+    encryption: Option<encryption::Encryption>,
+    revisions: usize,
+    linearized: bool,
+    linearize: bool,
+    compression: compression::Compression,
+    report: Vec<String>
 }
 impl Pdf {
     fn inner_file_name(&self) -> String {
@@ -84,21 +118,33 @@ impl Pdf {
     }
 }
 
-impl Pdf {
-    pub(crate) fn new(paths: DataPaths) -> Box<Self> {
-        Box::from(Pdf {
-            paths: paths,
-            data: state_Doc::Stub
-        })
-    }
-}
-
 impl Heaped for Pdf {
 
     fn new(paths: DataPaths) -> Box<Self> {
         Box::from(Pdf {
             paths: paths,
-            data: state_Doc::Stub
+            data: state_Doc::Stub,
+            rewrite_xmp: OPTIONS.rewrite_xmp,
+            annotations: annotations::AnnotationPolicy {
+                scrub_authorship: OPTIONS.scrub_authorship,
+                author: OPTIONS.annotation_author.clone(),
+                remove_markup: OPTIONS.remove_markup
+            },
+            harden: OPTIONS.harden_pdf,
+            forms: forms::FormPolicy {
+                clear: OPTIONS.clear_forms,
+                remove_xfa: OPTIONS.remove_xfa,
+                flatten: OPTIONS.flatten_forms
+            },
+            images: Cleaner::from_options(),
+            id_mode: OPTIONS.pdf_id,
+            password: OPTIONS.pdf_password.clone(),
+            encryption: None,
+            revisions: 0,
+            linearized: false,
+            linearize: OPTIONS.linearize_pdf,
+            compression: compression::Compression::default(),
+            report: Vec::new()
         })
     }
 
@@ -106,8 +152,18 @@ impl Heaped for Pdf {
         self.paths.old_owned()
     }
 
+    fn report(&self) -> Vec<String> {
+        self.report.clone()
+    }
+
     fn load(&mut self) -> Result<(), PurgeErr> {
-        self.data = state_Doc::Data(Document::load(&self.paths.old())?);
+        let bytes = std::fs::read(self.paths.old())?;
+        let mut document = encryption::load(&bytes, self.password.as_deref())?;
+        self.revisions = revisions::count(&bytes, &document);
+        self.linearized = revisions::is_linearized(&document);
+        self.encryption = encryption::Encryption::open(&mut document)?;
+        self.compression = compression::Compression::of(&document);
+        self.data = state_Doc::Data(document);
 
         Ok(())
     }
@@ -115,6 +171,41 @@ impl Heaped for Pdf {
     fn process(&mut self) -> Result<(), PurgeErr>  {
 
         let mut dirty_objs:Vec<dirty_Objs> = Vec::new();
+        let rewrite_xmp = self.rewrite_xmp;
+        self.report.clear();
+        if self.encryption.is_some() {
+            // Without --pdf-password lopdf only gets through with the empty user password.
+            let password = if self.password.is_some() { "the given password" } else { "the empty user password" };
+            self.report.push(format!("decrypted with {}, saved with the same encryption and permissions", password));
+            // The encryption key is derived from the first /ID string.
+            if self.id_mode != id::IdMode::Keep {
+                self.id_mode = id::IdMode::Keep;
+                self.report.push("kept the trailer ID, the encryption depends on it".to_string());
+            }
+        }
+        if self.linearized {
+            if !self.linearize {
+                self.report.push("saved without linearization (fast web view), --linearize-pdf restores it".to_string());
+            } else if !linearize::available() {
+                self.linearize = false;
+                self.report.push("saved without linearization (fast web view), qpdf isn't installed".to_string());
+            } else if matches!(self.id_mode, id::IdMode::Remove | id::IdMode::Random) {
+                // qpdf keeps the first half of an ID and makes up the rest, or all of it when there is none.
+                return Err(PurgeErr::from(PdfStructureErr::new(
+                    "qpdf writes a trailer ID of its own, --linearize-pdf only goes with --pdf-id=keep or hash")))
+            } else {
+                self.report.push("linearized again with qpdf".to_string());
+            }
+        }
+        // lopdf writes encrypted documents without object streams, it has no key left to encrypt new ones with.
+        if self.compression.object_streams && self.encryption.is_some() {
+            self.report.push("saved objects from object streams one by one, as the file is encrypted".to_string());
+        }
+        // Saving writes every live object once, so the history of incremental updates is gone.
+        if self.revisions > 1 {
+            let earlier = self.revisions - 1;
+            self.report.push(format!("discarded {} earlier revision{}", earlier, if earlier == 1 { "" } else { "s" }));
+        }
 
         let mut doc = match &mut self.data {
             state_Doc::Stub => {unreachable!("This can and will never happen. \
@@ -122,48 +213,37 @@ impl Heaped for Pdf {
             state_Doc::Data(document) => document
         };
 
-        for (object_id, object) in doc.objects.iter() {
-            if let Ok(dict) = object.as_dict() {
-                if dict.len() == 0 {
-                    continue
+        // Document information lives in the dictionary the trailer /Info points at.
+        // The same keys elsewhere (outline /Title, annotation /Subject) are content, not metadata.
+        match doc.trailer.get(b"Info") {
+            Ok(Object::Reference(info_id)) => {
+                if let Ok(dict) = doc.get_dictionary(*info_id) {
+                    let dirty_keys = metadata_keys(dict);
+                    if dirty_keys.len() == dict.len() {
+                        dirty_objs.push(dirty_Objs::Empty(*info_id))
+                    } else if !dirty_keys.is_empty() {
+                        dirty_objs.push(dirty_Objs::Dict(*info_id, dirty_keys))
+                    }
                 }
-                let dirty_keys: Vec<Vec<u8>> = dict
-                    .iter()
-                    .filter_map(|(key, value)|
-                        {
-                        if let Ok(str_key) = from_utf8(key) {
-                                if PDF_METADATA_KEYS.contains(&str_key) {
-                                    Some(key.clone())
-                                }
-                                else {None}
-                            } else {
-                            unreachable!("It's not supposed to happen. XMP keys are valid UTF-8 strings.\
-                            I will not parse a broken PDF and refuse to believe it can happen.")
-                        }
-                        })
-                    // .filter(Option::is_some)
-                    .collect();
-                if dirty_keys.len() == dict.len() {
-                    dirty_objs.push(dirty_Objs::Empty(object_id.clone()))
-                } else if dirty_keys.len() != 0 {
-                    dirty_objs.push(dirty_Objs::Dict(object_id.clone(), dirty_keys))
+            },
+            Ok(Object::Dictionary(dict)) => {
+                let dirty_keys = metadata_keys(dict);
+                if let Ok(dict) = doc.trailer.get_mut(b"Info").and_then(Object::as_dict_mut) {
+                    for key in dirty_keys {
+                        dict.remove(key.as_slice());
+                    }
                 }
-            }
-            else if object
+            },
+            _ => {}
+        }
+
+        for (object_id, object) in doc.objects.iter() {
+            if object
                 .as_stream().ok()
                 .and_then(|strm| is_xmp_meta_stream(strm))
                 .unwrap_or(false) {
-                dirty_objs.push(dirty_Objs::Stream(object_id.clone()));
+                dirty_objs.push(dirty_Objs::Stream(*object_id));
             }
-            // else if let Ok(Ok(strm)) = object.as_stream() {
-            //     if strm.dict.has("Subtype".as_bytes()) & strm.dict.has("Type".as_bytes()) {
-            //         if (strm.dict.get("Subtype".as_bytes()).unwrap().as_name_str().unwrap() == "XML")
-            //             &&  (strm.dict.get("Type".as_bytes()).unwrap().as_name_str().unwrap() == "Metadata") {
-            //
-            //         dirty_objs.push(dirty_Objs::Stream(object_id.clone()))
-            //             }
-            //     }
-            // }
         }
 
         for obj in dirty_objs {
@@ -171,7 +251,7 @@ impl Heaped for Pdf {
                 dirty_Objs::Empty(id) => {doc.objects.remove(&id);},
 
                 dirty_Objs::Dict(id, keys) => {
-                    if let Ok(mut dict) = doc.get_object_mut(id.clone()).unwrap().as_dict_mut() {
+                    if let Ok(dict) = doc.get_dictionary_mut(id) {
                         for key in keys {
                             dict.remove(key.as_slice());
                         }
@@ -179,33 +259,45 @@ impl Heaped for Pdf {
                 },
 
                 dirty_Objs::Stream(id) => {
+                    // A packet that doesn't parse is removed like before.
+                    if rewrite_xmp && doc.get_object_mut(id)
+                        .and_then(|object| object.as_stream_mut())
+                        .map(|strm| rewrite_xmp_stream(strm).is_ok())
+                        .unwrap_or(false) {
+                        continue
+                    }
                     doc.objects.remove(&id);
-
-                    // Potentially we can instead overwrite the metadata with empty values, so it looks less conscious.
-                    // Probably has to be made into a CMD argument feature.
-
-                    // if let Ok(mut strm) = doc.get_object_mut(id.clone()).unwrap().as_stream_mut() {
-                    //     let byte_slice: &[u8] = &strm.content;
-                    //     let string_slice: &str = unsafe {
-                    //
-                    //         std::str::from_utf8_unchecked(byte_slice)
-                    //     };
-                    //     let mut loaded = xmp_toolkit::XmpMeta::from_str(string_slice)?;
-                    //     match clean_xmp(loaded) {
-                    //         Ok(cleaned)=> {
-                    //         let cleaned_xmp = cleaned.to_string_with_options(ToStringOptions::default())?;
-                    //         strm.set_content(cleaned_xmp.as_bytes().to_vec());
-                    //         },
-                    //         Err(err) => {
-                    //             panic!("pdf error!!");
-                    //             return Err(PurgeErr::from(err))
-                    //         }
-                    //     }
-                    // }
                 }
             }
         }
 
+        for counts in annotations::scrub(doc, &self.annotations) {
+            let mut done: Vec<String> = Vec::new();
+            if counts.scrubbed > 0 {
+                done.push(format!("scrubbed {} annotations", counts.scrubbed));
+            }
+            if counts.removed > 0 {
+                done.push(format!("removed {} annotations", counts.removed));
+            }
+            self.report.push(format!("page {}: {}", counts.page, done.join(", ")));
+        }
+        if self.annotations.scrub_authorship {
+            outlines::scrub_metadata(doc);
+        }
+        if self.harden {
+            self.report.extend(hardening::harden(doc));
+        }
+        self.report.extend(forms::clean(doc, self.forms));
+        self.report.extend(private::remove_application_data(doc));
+        self.report.extend(images::clean_jpegs(doc, &self.images));
+
+        // The trailer /Info, catalog /Metadata and replies to removed annotations may point at what was just removed.
+        refs::drop_dangling(doc);
+        doc.prune_objects();
+        self.compression.restore(doc);
+
+        id::apply(doc, self.id_mode)?;
+
         Ok(())
     }
 
@@ -214,16 +306,126 @@ impl Heaped for Pdf {
             state_Doc::Stub => {unreachable!("Not possible.")}
             state_Doc::Data(ref mut data) => {data}
         };
-        data.save(self.paths.old())?;
-        // if let Err(hr) = std::fs::remove_file(&self.paths.old()) {
-        //     std::fs::remove_file(&self.paths.temp_path);
-        //     return Err(PurgeErr::from(hr))
-        // };
-        // if let Err(hr) = std::fs::rename(self.paths.temp(), self.paths.old()) {
-        //     std::fs::remove_file(&self.paths.temp());
-        //     return Err(PurgeErr::from(hr))
-        // }
+        if let Some(encryption) = &self.encryption {
+            encryption.seal(data)?;
+        }
+        let mut saved: Vec<u8> = Vec::new();
+        // Files that used object streams get them again; older ones keep a classic cross-reference table.
+        let options = SaveOptions::builder()
+            .use_object_streams(self.compression.object_streams)
+            .use_xref_streams(self.compression.object_streams)
+            .build();
+        data.save_with_options(&mut saved, options)?;
+        revisions::verify_single(&saved, data)?;
+        // The original stays as it is until the cleaned copy is completely on disk.
+        let written = File::create(self.paths.temp())
+            .and_then(|mut temp| {
+                temp.write_all(&saved)?;
+                temp.sync_all()
+            });
+        if let Err(hr) = written {
+            let _ = std::fs::remove_file(self.paths.temp());
+            return Err(PurgeErr::from(hr))
+        }
+        if self.linearized && self.linearize {
+            let password = self.encryption.as_ref().and(self.password.as_deref());
+            let deterministic_id = self.id_mode == id::IdMode::Hash;
+            if let Err(info) = linearize::linearize(self.paths.temp(), password, deterministic_id) {
+                let _ = std::fs::remove_file(self.paths.temp());
+                return Err(PurgeErr::from(PdfStructureErr::new(&format!("linearizing with qpdf failed: {}", info))))
+            }
+        }
+        if let Err(hr) = std::fs::rename(self.paths.temp(), self.paths.old()) {
+            let _ = std::fs::remove_file(self.paths.temp());
+            return Err(PurgeErr::from(hr))
+        }
         Ok(())
     }
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::xref::XrefType;
+    use lopdf::{dictionary, Document};
+    use crate::pdf::test_support::{self, contains, TITLE};
+    use super::*;
+
+    /// The shared test document, saved with or without object streams.
+    fn file(object_streams: bool) -> Vec<u8> {
+        let mut doc = test_support::document();
+        let mut bytes = Vec::new();
+        if object_streams {
+            doc.save_modern(&mut bytes).expect("saves");
+        } else {
+            doc.reference_table.cross_reference_type = XrefType::CrossReferenceTable;
+            doc.save_to(&mut bytes).expect("saves");
+        }
+        bytes
+    }
+
+    /// Cleans `bytes` as a file in the temp directory, returning the report and what was saved.
+    fn cleaned(name: &str, bytes: &[u8]) -> (Vec<String>, Vec<u8>) {
+        let path = std::env::temp_dir().join(format!("{}-{}.pdf", name, std::process::id()));
+        std::fs::write(&path, bytes).expect("writes");
+        let entry = walkdir::WalkDir::new(&path).into_iter().next().expect("entry").expect("entry");
+        let mut pdf = Pdf::new(DataPaths::new(entry));
+        pdf.load().expect("loads");
+        pdf.process().expect("cleans");
+        let report = pdf.report();
+        pdf.save().expect("saves");
+        let saved = std::fs::read(&path).expect("reads");
+        let _ = std::fs::remove_file(&path);
+        (report, saved)
+    }
+
+    #[test]
+    fn object_streams_are_written_again() {
+        let original = file(true);
+        assert!(contains(&original, b"/ObjStm"));
+        let (report, saved) = cleaned("object-streams", &original);
+        assert!(contains(&saved, b"/ObjStm") && contains(&saved, b"/XRef"));
+        assert!(!report.iter().any(|line| line.contains("object streams")));
+
+        let doc = Document::load_mem(&saved).expect("loads");
+        assert!(doc.catalog().is_ok());
+        assert!(!contains(&saved, TITLE));
+    }
+
+    #[test]
+    fn classic_files_stay_classic() {
+        let (_, saved) = cleaned("classic", &file(false));
+        assert!(!contains(&saved, b"/ObjStm") && contains(&saved, b"\nxref\n"));
+        assert!(Document::load_mem(&saved).expect("loads").catalog().is_ok());
+    }
+
+    #[test]
+    fn the_empty_user_password_is_reported() {
+        let mut doc = test_support::document();
+        doc.trailer.set("ID", vec![Object::string_literal(b"0123456789abcdef".to_vec()), Object::string_literal(b"0123456789abcdef".to_vec())]);
+        let (report, saved) = cleaned("empty-password", &test_support::aes_encrypted(doc, ""));
+        assert!(report.contains(&"decrypted with the empty user password, saved with the same encryption and permissions".to_string()), "{:?}", report);
+        assert!(contains(&saved, b"/Encrypt") && !contains(&saved, TITLE));
+    }
+
+    const XMP: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?><x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description rdf:about="" xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:xmp="http://ns.adobe.com/xap/1.0/"><pdfaid:part>2</pdfaid:part><pdfaid:conformance>B</pdfaid:conformance><dc:creator><rdf:Seq><rdf:li>Jane Doe</rdf:li></rdf:Seq></dc:creator><xmp:CreatorTool>Secret Writer</xmp:CreatorTool></rdf:Description></rdf:RDF></x:xmpmeta><?xpacket end="w"?>"#;
+
+    #[test]
+    fn xmp_rewrite_keeps_pdfa_identification() {
+        let mut strm = Stream::new(dictionary! { "Type" => "Metadata", "Subtype" => "XML" }, XMP.as_bytes().to_vec());
+        assert_eq!(is_xmp_meta_stream(&strm), Some(true));
+        strm.compress().expect("compresses");
+        rewrite_xmp_stream(&mut strm).expect("rewrites");
+
+        assert!(!strm.dict.has(b"Filter"));
+        let xmp = String::from_utf8(strm.content.clone()).expect("utf-8");
+        assert!(xmp.contains("pdfaid:part>2<") && xmp.contains("pdfaid:conformance>B<"), "{}", xmp);
+        assert!(!xmp.contains("Jane Doe") && !xmp.contains("Secret Writer"), "{}", xmp);
+    }
+
+    #[test]
+    fn undecodable_xmp_is_an_error() {
+        let mut strm = Stream::new(dictionary! { "Type" => "Metadata", "Subtype" => "XML" }, b"<x:xmpmeta \xff\xfe>".to_vec());
+        assert!(rewrite_xmp_stream(&mut strm).is_err());
+    }
+}
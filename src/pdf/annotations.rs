@@ -0,0 +1,250 @@
+use std::collections::BTreeSet;
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+
+/// Who wrote an annotation and when.
+const AUTHORSHIP_KEYS: [&[u8]; 3] = [b"T", b"M", b"CreationDate"];
+
+/// Annotations a reviewer adds on top of the page, as opposed to links, widgets and the like.
+const MARKUP_SUBTYPES: [&[u8]; 17] = [
+    b"Text", b"FreeText", b"Line", b"Square", b"Circle", b"Polygon", b"PolyLine", b"Highlight", b"Underline",
+    b"Squiggly", b"StrikeOut", b"Caret", b"Ink", b"Stamp", b"FileAttachment", b"Sound", b"Redact",
+];
+
+/// What happens to annotations.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AnnotationPolicy {
+    /// Remove authors, dates, popups and replies.
+    pub(crate) scrub_authorship: bool,
+    /// Put this name in /T instead of removing it.
+    pub(crate) author: Option<String>,
+    /// Remove every markup annotation.
+    pub(crate) remove_markup: bool,
+}
+
+/// What was done to the annotations of one page.
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct PageCounts {
+    pub(crate) page: u32,
+    pub(crate) scrubbed: usize,
+    pub(crate) removed: usize,
+}
+
+/// Annotation ids of every page, by page number.
+pub(crate) fn page_annotations(doc: &Document) -> Vec<(u32, Vec<ObjectId>)> {
+    doc.get_pages()
+        .into_iter()
+        .map(|(number, page_id)| {
+            let ids = doc.get_dictionary(page_id).ok()
+                .and_then(|page| page.get(b"Annots").ok())
+                .and_then(|annots| doc.dereference(annots).ok())
+                .and_then(|(_, annots)| annots.as_array().ok())
+                .map(|annots| annots.iter().filter_map(|annot| annot.as_reference().ok()).collect())
+                .unwrap_or_default();
+            (number, ids)
+        })
+        .collect()
+}
+
+fn subtype(annot: &Dictionary) -> Option<&[u8]> {
+    annot.get(b"Subtype").and_then(Object::as_name).ok()
+}
+
+/// A reply in a comment thread. /RT /Group also uses /IRT, but for annotations that belong together.
+fn is_reply(annot: &Dictionary) -> bool {
+    annot.has(b"IRT") && annot.get(b"RT").and_then(Object::as_name).map(|rt| rt != b"Group").unwrap_or(true)
+}
+
+fn is_markup(annot: &Dictionary) -> bool {
+    subtype(annot).map(|subtype| MARKUP_SUBTYPES.contains(&subtype)).unwrap_or(false)
+}
+
+fn removes(annot: &Dictionary, policy: &AnnotationPolicy) -> bool {
+    let is_popup = subtype(annot) == Some(b"Popup");
+    (policy.remove_markup && (is_markup(annot) || is_popup))
+        || (policy.scrub_authorship && (is_popup || is_reply(annot)))
+}
+
+/// A PDF text string: PDFDocEncoding covers ASCII, anything else goes in UTF-16BE with a byte order mark.
+fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::string_literal(text)
+    }
+    let mut bytes = vec![0xfe, 0xff];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+    Object::String(bytes, StringFormat::Literal)
+}
+
+/// Removes or renames authors and dates of the annotation. Widgets keep their /T,
+/// which on a widget is the form field name, not an author.
+fn scrub_authorship(annot: &mut Dictionary, author: Option<&str>) -> bool {
+    let is_widget = subtype(annot) == Some(b"Widget");
+    let mut changed = false;
+    for key in AUTHORSHIP_KEYS {
+        if key == b"T" && is_widget {
+            continue
+        }
+        changed |= annot.remove(key).is_some();
+    }
+    // Popups and replies go away with the annotation itself, see `removes`.
+    changed |= annot.remove(b"Popup").is_some();
+    // Only markup annotations have an author to show.
+    if let (Some(author), true) = (author, is_markup(annot)) {
+        annot.set("T", text_string(author));
+    }
+    changed
+}
+
+/// Drops `removed` from the /Annots array of the page, wherever that array lives.
+pub(crate) fn drop_from_page(doc: &mut Document, page_id: ObjectId, removed: &BTreeSet<ObjectId>) {
+    let annots_id = match doc.get_dictionary(page_id).and_then(|page| page.get(b"Annots")) {
+        Ok(Object::Reference(id)) => Some(*id),
+        _ => None,
+    };
+    let annots = match annots_id {
+        Some(id) => doc.get_object_mut(id).and_then(Object::as_array_mut),
+        None => doc.get_dictionary_mut(page_id)
+            .and_then(|page| page.get_mut(b"Annots"))
+            .and_then(Object::as_array_mut),
+    };
+    if let Ok(annots) = annots {
+        annots.retain(|annot| annot.as_reference().map(|id| !removed.contains(&id)).unwrap_or(true));
+    }
+}
+
+/// Applies `policy` to the annotations of every page. Returns counts for the pages where something changed.
+///
+/// Removed annotations are taken out of the document; whatever still pointed at them
+/// (a popup's /Parent, a reply's /IRT) is left for `refs::drop_dangling`.
+pub(crate) fn scrub(doc: &mut Document, policy: &AnnotationPolicy) -> Vec<PageCounts> {
+    let pages = doc.get_pages();
+    let mut counts: Vec<PageCounts> = Vec::new();
+
+    for (page, ids) in page_annotations(doc) {
+        let mut current = PageCounts { page, ..PageCounts::default() };
+        let mut removed: BTreeSet<ObjectId> = BTreeSet::new();
+
+        for id in ids {
+            let annot = match doc.get_dictionary_mut(id) {
+                Ok(annot) => annot,
+                Err(_) => continue,
+            };
+            if removes(annot, policy) {
+                removed.insert(id);
+            } else if policy.scrub_authorship && scrub_authorship(annot, policy.author.as_deref()) {
+                current.scrubbed += 1;
+            }
+        }
+
+        if !removed.is_empty() {
+            if let Some(page_id) = pages.get(&page) {
+                drop_from_page(doc, *page_id, &removed);
+            }
+            for id in removed.iter() {
+                doc.objects.remove(id);
+            }
+            current.removed = removed.len();
+        }
+        if current.scrubbed > 0 || current.removed > 0 {
+            counts.push(current);
+        }
+    }
+    counts
+}
+
+/// Removes every annotation of the given subtype from every page. Returns how many were removed.
+pub(crate) fn remove_subtype(doc: &mut Document, kind: &[u8]) -> usize {
+    let pages = doc.get_pages();
+    let mut total = 0;
+    for (page, ids) in page_annotations(doc) {
+        let removed: BTreeSet<ObjectId> = ids.into_iter()
+            .filter(|id| doc.get_dictionary(*id).map(|annot| subtype(annot) == Some(kind)).unwrap_or(false))
+            .collect();
+        if removed.is_empty() {
+            continue
+        }
+        if let Some(page_id) = pages.get(&page) {
+            drop_from_page(doc, *page_id, &removed);
+        }
+        for id in removed.iter() {
+            doc.objects.remove(id);
+        }
+        total += removed.len();
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::dictionary;
+    use crate::pdf::test_support::{self, PAGE_ID};
+    use super::*;
+
+    /// Ids of the annotations `annotated` adds: a comment with its popup, a reply, a form widget and a link.
+    struct Annots {
+        comment: ObjectId,
+        popup: ObjectId,
+        reply: ObjectId,
+        widget: ObjectId,
+        link: ObjectId,
+    }
+
+    fn annotated() -> (Document, Annots) {
+        let mut doc = test_support::document();
+        let comment = doc.new_object_id();
+        let popup = doc.add_object(dictionary! { "Type" => "Annot", "Subtype" => "Popup", "Parent" => comment });
+        doc.objects.insert(comment, Object::Dictionary(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Text",
+            "T" => Object::string_literal("Jane Doe"),
+            "M" => Object::string_literal("D:20240101000000Z"),
+            "Contents" => Object::string_literal("looks good"),
+            "Popup" => popup,
+        }));
+        let reply = doc.add_object(dictionary! { "Type" => "Annot", "Subtype" => "Text", "IRT" => comment, "T" => Object::string_literal("John Roe") });
+        let widget = doc.add_object(dictionary! { "Type" => "Annot", "Subtype" => "Widget", "T" => Object::string_literal("name") });
+        let link = doc.add_object(dictionary! { "Type" => "Annot", "Subtype" => "Link" });
+        let ids: Vec<Object> = [comment, popup, reply, widget, link].iter().map(|id| Object::Reference(*id)).collect();
+        doc.get_dictionary_mut(PAGE_ID).expect("page").set("Annots", ids);
+        (doc, Annots { comment, popup, reply, widget, link })
+    }
+
+    fn page_annots(doc: &Document) -> Vec<ObjectId> {
+        page_annotations(doc).into_iter().flat_map(|(_, ids)| ids).collect()
+    }
+
+    #[test]
+    fn scrubbing_authorship_removes_authors_popups_and_replies() {
+        let (mut doc, annots) = annotated();
+        let policy = AnnotationPolicy { scrub_authorship: true, ..AnnotationPolicy::default() };
+        let counts = scrub(&mut doc, &policy);
+        assert_eq!(counts.len(), 1);
+        assert_eq!((counts[0].page, counts[0].scrubbed, counts[0].removed), (1, 1, 2));
+
+        assert_eq!(page_annots(&doc), vec![annots.comment, annots.widget, annots.link]);
+        assert!(!doc.objects.contains_key(&annots.popup) && !doc.objects.contains_key(&annots.reply));
+        let comment = doc.get_dictionary(annots.comment).expect("comment");
+        assert!(!comment.has(b"T") && !comment.has(b"M") && !comment.has(b"Popup"));
+        assert!(comment.has(b"Contents"));
+        let widget = doc.get_dictionary(annots.widget).expect("widget");
+        assert_eq!(widget.get(b"T").and_then(Object::as_str).ok(), Some(b"name".as_slice()));
+    }
+
+    #[test]
+    fn a_replacement_author_goes_on_markup_only() {
+        let (mut doc, annots) = annotated();
+        let policy = AnnotationPolicy { scrub_authorship: true, author: Some("Zoë".to_string()), ..AnnotationPolicy::default() };
+        scrub(&mut doc, &policy);
+        let comment = doc.get_dictionary(annots.comment).expect("comment");
+        assert_eq!(comment.get(b"T").and_then(Object::as_str).ok(), Some(b"\xfe\xff\0Z\0o\0\xeb".as_slice()));
+        assert!(!doc.get_dictionary(annots.link).expect("link").has(b"T"));
+    }
+
+    #[test]
+    fn removing_markup_keeps_widgets_and_links() {
+        let (mut doc, annots) = annotated();
+        let policy = AnnotationPolicy { remove_markup: true, ..AnnotationPolicy::default() };
+        let counts = scrub(&mut doc, &policy);
+        assert_eq!((counts[0].scrubbed, counts[0].removed), (0, 3));
+        assert_eq!(page_annots(&doc), vec![annots.widget, annots.link]);
+    }
+}
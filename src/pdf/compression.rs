@@ -0,0 +1,51 @@
+use std::collections::BTreeSet;
+use lopdf::xref::XrefEntry;
+use lopdf::{Document, Object, ObjectId};
+
+/// Which streams of the original document were compressed, so saving doesn't leave them bigger.
+///
+/// lopdf keeps loaded streams as they were, but rewritten XMP and streams added while
+/// cleaning are plain. Whether the original packed objects into object streams is kept too,
+/// so the cleaned file is written the same way.
+#[derive(Default)]
+pub(crate) struct Compression {
+    streams: BTreeSet<ObjectId>,
+    any: bool,
+    /// Some objects came out of object streams.
+    pub(crate) object_streams: bool,
+}
+
+fn is_compressed(object: &Object) -> bool {
+    object.as_stream().map(|strm| strm.dict.has(b"Filter")).unwrap_or(false)
+}
+
+/// PDF/A does not allow filters on metadata streams, so those stay plain.
+fn may_compress(object: &Object) -> bool {
+    object.type_name().map(|kind| kind != b"Metadata").unwrap_or(true)
+}
+
+impl Compression {
+    pub(crate) fn of(doc: &Document) -> Compression {
+        let streams: BTreeSet<ObjectId> = doc.objects.iter()
+            .filter(|(_, object)| is_compressed(object))
+            .map(|(id, _)| *id)
+            .collect();
+        let any = !streams.is_empty();
+        let object_streams = doc.reference_table.entries.values().any(|entry| matches!(entry, XrefEntry::Compressed { .. }));
+        Compression { streams, any, object_streams }
+    }
+
+    /// Compresses plain streams that were compressed before, and new streams if the original used compression at all.
+    pub(crate) fn restore(&self, doc: &mut Document) {
+        for (id, object) in doc.objects.iter_mut() {
+            let wanted = self.streams.contains(id) || (self.any && !doc.reference_table.entries.contains_key(&id.0));
+            if !wanted || is_compressed(object) || !may_compress(object) {
+                continue
+            }
+            // compress() leaves the stream plain when that is smaller anyway; failing to compress isn't worth failing the file.
+            if let Ok(strm) = object.as_stream_mut() {
+                let _ = strm.compress();
+            }
+        }
+    }
+}
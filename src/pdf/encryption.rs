@@ -0,0 +1,149 @@
+use lopdf::encryption::{encrypt_object, EncryptionState};
+use lopdf::{Document, LoadOptions, Object, ObjectId};
+use crate::errors::error::{PdfEncryptedErr, PurgeErr};
+
+/// The standard security handler of an encrypted document, kept so it can be encrypted again on save.
+///
+/// lopdf decrypts while loading and takes the /Encrypt dictionary out of the document. Sealing
+/// puts an equivalent dictionary back under its old object number and encrypts every object with
+/// the same file key, so the cleaned file opens with the same passwords and has the same permissions.
+/// The trailer /ID is kept as it is, the key of RC4 and AES-128 files depends on it.
+pub(crate) struct Encryption {
+    state: EncryptionState,
+    dict_id: Option<ObjectId>,
+}
+
+fn encrypted(info: &str) -> PurgeErr {
+    PurgeErr::from(PdfEncryptedErr::new(info))
+}
+
+/// Whether a file lopdf couldn't load at all looks encrypted, to tell the user why.
+pub(crate) fn looks_encrypted(bytes: &[u8]) -> bool {
+    bytes.windows(b"/Encrypt".len()).any(|window| window == b"/Encrypt")
+}
+
+/// Loads `bytes`, decrypting with `password` if the file needs one.
+/// Files with an empty user password are decrypted without it.
+pub(crate) fn load(bytes: &[u8], password: Option<&str>) -> Result<Document, PurgeErr> {
+    let loaded = match password {
+        Some(password) => Document::load_mem_with_options(bytes, LoadOptions::with_password(password)),
+        None => Document::load_mem(bytes),
+    };
+    loaded.map_err(|err| match err {
+        lopdf::Error::InvalidPassword => encrypted("the password doesn't open it"),
+        err if looks_encrypted(bytes) => encrypted(&format!("can't be read: {}", err)),
+        err => PurgeErr::from(err),
+    })
+}
+
+impl Encryption {
+    /// Takes the security handler of a document `load` decrypted. A document that is still
+    /// encrypted needed a password that wasn't given.
+    pub(crate) fn open(doc: &mut Document) -> Result<Option<Encryption>, PurgeErr> {
+        if doc.is_encrypted() {
+            return Err(encrypted("pass its password with --pdf-password=<password>"))
+        }
+        Ok(doc.encryption_state.take().map(|state| Encryption {
+            dict_id: state.encrypt_object_id(),
+            state,
+        }))
+    }
+
+    /// Encrypts `doc` again before it is written.
+    pub(crate) fn seal(&self, doc: &mut Document) -> Result<(), PurgeErr> {
+        let dict = self.state.encode().map_err(lopdf::Error::from)?;
+        for (id, object) in doc.objects.iter_mut() {
+            encrypt_object(&self.state, *id, object).map_err(lopdf::Error::from)?;
+        }
+        let dict_id = self.dict_id
+            .filter(|id| !doc.objects.contains_key(id))
+            .unwrap_or_else(|| doc.new_object_id());
+        doc.objects.insert(dict_id, Object::Dictionary(dict));
+        doc.trailer.set("Encrypt", Object::Reference(dict_id));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::{EncryptionVersion, Permissions};
+    use crate::pdf::test_support::{self, contains, saved, CONTENT, CONTENT_ID, INFO_ID, TITLE};
+    use super::*;
+
+    /// The shared document with the trailer /ID the RC4 and AES-128 keys depend on.
+    fn document() -> Document {
+        let mut doc = test_support::document();
+        doc.trailer.set("ID", vec![Object::string_literal(b"0123456789abcdef".to_vec()), Object::string_literal(b"0123456789abcdef".to_vec())]);
+        doc
+    }
+
+    fn encrypted_with(version: EncryptionVersion) -> Vec<u8> {
+        let mut doc = document();
+        let state = EncryptionState::try_from(version).expect("valid encryption");
+        doc.encrypt(&state).expect("encrypts");
+        saved(&mut doc)
+    }
+
+    fn rc4(doc: &Document) -> Vec<u8> {
+        encrypted_with(EncryptionVersion::V2 {
+            document: doc,
+            owner_password: "owner",
+            user_password: "user",
+            key_length: 128,
+            permissions: Permissions::PRINTABLE,
+        })
+    }
+
+    fn aes(user_password: &str) -> Vec<u8> {
+        test_support::aes_encrypted(document(), user_password)
+    }
+
+    /// Opens `bytes` like the cleaner does, seals and saves them, and opens the result again.
+    fn round_trip(bytes: &[u8], password: Option<&str>) -> Document {
+        let mut doc = load(bytes, password).expect("loads");
+        let encryption = Encryption::open(&mut doc).expect("opens").expect("is encrypted");
+        assert_eq!(doc.get_dictionary(INFO_ID).and_then(|info| info.get(b"Title")).and_then(Object::as_str).ok(), Some(TITLE));
+        encryption.seal(&mut doc).expect("seals");
+        let cleaned = saved(&mut doc);
+        assert!(!contains(&cleaned, TITLE) && !contains(&cleaned, CONTENT));
+
+        let mut reopened = load(&cleaned, password).expect("loads again");
+        let state = reopened.encryption_state.as_ref().expect("still encrypted");
+        assert!(state.permissions().contains(Permissions::PRINTABLE) && !state.permissions().contains(Permissions::MODIFIABLE));
+        assert!(Encryption::open(&mut reopened).expect("opens again").is_some());
+        reopened
+    }
+
+    fn title_and_content(doc: &Document) -> (Vec<u8>, Vec<u8>) {
+        let title = doc.get_dictionary(INFO_ID).and_then(|info| info.get(b"Title")).and_then(Object::as_str).expect("title");
+        let content = doc.get_object(CONTENT_ID).and_then(Object::as_stream).expect("content");
+        (title.to_vec(), content.content.clone())
+    }
+
+    #[test]
+    fn rc4_documents_are_encrypted_again() {
+        let original = rc4(&document());
+        let reopened = round_trip(&original, Some("user"));
+        assert_eq!(title_and_content(&reopened), (TITLE.to_vec(), CONTENT.to_vec()));
+    }
+
+    #[test]
+    fn aes_documents_are_encrypted_again() {
+        let reopened = round_trip(&aes("user"), Some("owner"));
+        assert_eq!(title_and_content(&reopened), (TITLE.to_vec(), CONTENT.to_vec()));
+    }
+
+    #[test]
+    fn an_empty_user_password_needs_no_option() {
+        let reopened = round_trip(&aes(""), None);
+        assert_eq!(title_and_content(&reopened), (TITLE.to_vec(), CONTENT.to_vec()));
+    }
+
+    #[test]
+    fn missing_and_wrong_passwords_are_errors() {
+        let original = aes("user");
+        let mut doc = load(&original, None).expect("loads without decrypting");
+        assert!(matches!(Encryption::open(&mut doc), Err(PurgeErr::PdfEncrypted(_))));
+        assert!(matches!(load(&original, Some("wrong")), Err(PurgeErr::PdfEncrypted(_))));
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::BTreeSet;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use crate::pdf::annotations;
+use crate::pdf::objects::{catalog_dict, catalog_dict_mut, text};
+
+/// Field keys holding what was filled in: the value, the default value and the selected options of a choice field.
+const VALUE_KEYS: [&[u8]; 3] = [b"V", b"DV", b"I"];
+
+/// Widget annotation flags that keep it off the page: Hidden and NoView.
+const NOT_DRAWN: i64 = 2 | 32;
+
+/// What happens to the interactive form. Without any of these the form data is only reported.
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct FormPolicy {
+    /// Remove field values so the form is blank again.
+    pub(crate) clear: bool,
+    /// Remove the XFA datasets packet, the XFA copy of the filled in data.
+    pub(crate) remove_xfa: bool,
+    /// Draw the fields into the page content and remove the form.
+    pub(crate) flatten: bool,
+}
+
+fn acroform(doc: &Document) -> Option<&Dictionary> {
+    catalog_dict(doc, b"AcroForm")
+}
+
+fn acroform_mut(doc: &mut Document) -> Option<&mut Dictionary> {
+    catalog_dict_mut(doc, b"AcroForm")
+}
+
+/// Every field node with its fully qualified name, parents before their kids.
+fn fields(doc: &Document) -> Vec<(ObjectId, String)> {
+    fn walk(doc: &Document, kids: &[Object], parent: &str, visited: &mut BTreeSet<ObjectId>, out: &mut Vec<(ObjectId, String)>) {
+        for id in kids.iter().filter_map(|kid| kid.as_reference().ok()) {
+            let field = match doc.get_dictionary(id) {
+                Ok(field) if visited.insert(id) => field,
+                _ => continue,
+            };
+            let name = match field.get(b"T").and_then(Object::as_str) {
+                Ok(partial) if parent.is_empty() => text(partial),
+                Ok(partial) => format!("{}.{}", parent, text(partial)),
+                Err(_) => parent.to_string(),
+            };
+            out.push((id, name.clone()));
+            if let Ok(kids) = field.get(b"Kids").and_then(Object::as_array) {
+                walk(doc, kids, &name, visited, out);
+            }
+        }
+    }
+
+    let mut out: Vec<(ObjectId, String)> = Vec::new();
+    if let Some(roots) = acroform(doc).and_then(|form| form.get(b"Fields").and_then(Object::as_array).ok()) {
+        walk(doc, roots, "", &mut BTreeSet::new(), &mut out);
+    }
+    out
+}
+
+/// An attribute that may be set on the field or page itself or on any of its parents.
+fn inherited<'a>(doc: &'a Document, mut dict: &'a Dictionary, key: &[u8]) -> Option<&'a Object> {
+    for _ in 0..32 {
+        if let Ok(value) = dict.get(key) {
+            return Some(value)
+        }
+        dict = dict.get(b"Parent").and_then(Object::as_reference).and_then(|id| doc.get_dictionary(id)).ok()?;
+    }
+    None
+}
+
+fn is_widget(annot: &Dictionary) -> bool {
+    annot.get(b"Subtype").and_then(Object::as_name).map(|subtype| subtype == b"Widget").unwrap_or(false)
+}
+
+/// The names of the XFA packets, or `None` when the form has no XFA.
+/// An XFA stream on its own is one packet holding everything.
+fn xfa_packets(doc: &Document) -> Option<Vec<String>> {
+    match acroform(doc)?.get(b"XFA").ok()? {
+        Object::Array(packets) => Some(packets.iter()
+            .step_by(2)
+            .filter_map(|name| name.as_str().ok())
+            .map(text)
+            .collect()),
+        _ => Some(Vec::new()),
+    }
+}
+
+/// Removes values from every field. Text and choice widgets lose their appearance, which still
+/// shows the old value, and the viewer is asked to draw a new one; check boxes and radio buttons are turned off.
+fn clear(doc: &mut Document, fields: &[(ObjectId, String)]) -> usize {
+    let mut cleared = 0;
+    for (id, _) in fields {
+        if let Ok(field) = doc.get_dictionary_mut(*id) {
+            let mut changed = false;
+            for key in VALUE_KEYS {
+                changed |= field.remove(key).is_some();
+            }
+            if changed {
+                cleared += 1;
+            }
+        }
+    }
+
+    let widgets: Vec<(ObjectId, bool)> = annotations::page_annotations(doc).into_iter()
+        .flat_map(|(_, ids)| ids)
+        .filter_map(|id| {
+            let widget = doc.get_dictionary(id).ok().filter(|annot| is_widget(annot))?;
+            let is_button = inherited(doc, widget, b"FT").and_then(|ft| ft.as_name().ok()) == Some(b"Btn");
+            Some((id, is_button))
+        })
+        .collect();
+    for (id, is_button) in widgets.iter() {
+        if let Ok(widget) = doc.get_dictionary_mut(*id) {
+            if !is_button {
+                widget.remove(b"AP");
+            } else if widget.has(b"AS") {
+                widget.set("AS", Object::Name(b"Off".to_vec()));
+            }
+        }
+    }
+    if widgets.iter().any(|(_, is_button)| !is_button) {
+        if let Some(form) = acroform_mut(doc) {
+            form.set("NeedAppearances", true);
+        }
+    }
+    cleared
+}
+
+/// Removes the datasets packet, which holds the filled in data of an XFA form, keeping the template.
+/// A form that keeps all of XFA in a single stream loses the whole of it; the AcroForm fields stay.
+fn remove_xfa(doc: &mut Document) -> bool {
+    let form = match acroform_mut(doc) {
+        Some(form) => form,
+        None => return false,
+    };
+    let removed = match form.get_mut(b"XFA") {
+        Ok(Object::Array(packets)) => {
+            let before = packets.len();
+            let kept: Vec<Object> = packets.chunks(2)
+                .filter(|packet| packet[0].as_str().map(|name| name != b"datasets").unwrap_or(true))
+                .flatten()
+                .cloned()
+                .collect();
+            *packets = kept;
+            packets.len() != before
+        },
+        Ok(_) => form.remove(b"XFA").is_some(),
+        Err(_) => false,
+    };
+    if removed && !form.has(b"XFA") {
+        if let Ok(catalog) = doc.catalog_mut() {
+            catalog.remove(b"NeedsRendering");
+        }
+    }
+    removed
+}
+
+fn numbers(object: Option<&Object>) -> Option<Vec<f32>> {
+    object?.as_array().ok()?.iter().map(|number| number.as_float().ok()).collect()
+}
+
+/// The appearance stream a widget shows right now: its normal appearance, or the one for its state.
+fn appearance(doc: &Document, widget: &Dictionary) -> Option<ObjectId> {
+    let normal = widget.get(b"AP").ok()
+        .and_then(|ap| doc.dereference(ap).ok())
+        .and_then(|(_, ap)| ap.as_dict().ok())?
+        .get(b"N").ok()?;
+    match normal {
+        Object::Reference(id) if doc.get_object(*id).and_then(Object::as_stream).is_ok() => Some(*id),
+        _ => {
+            let states = doc.dereference(normal).ok()?.1.as_dict().ok()?;
+            let state = widget.get(b"AS").and_then(Object::as_name).ok()?;
+            states.get(state).and_then(Object::as_reference).ok()
+        },
+    }
+}
+
+/// The `cm` operands that put the appearance stream's bounding box, after its /Matrix, onto the widget rectangle.
+fn placement(appearance: &Stream, rect: &[f32]) -> Option<[f32; 6]> {
+    let bbox = numbers(appearance.dict.get(b"BBox").ok())?;
+    let matrix = numbers(appearance.dict.get(b"Matrix").ok()).unwrap_or_else(|| vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+    if bbox.len() != 4 || matrix.len() != 6 || rect.len() != 4 {
+        return None
+    }
+    let corners = [(bbox[0], bbox[1]), (bbox[2], bbox[1]), (bbox[0], bbox[3]), (bbox[2], bbox[3])]
+        .map(|(x, y)| (matrix[0] * x + matrix[2] * y + matrix[4], matrix[1] * x + matrix[3] * y + matrix[5]));
+    let (left, right) = corners.iter().fold((f32::MAX, f32::MIN), |(lo, hi), (x, _)| (lo.min(*x), hi.max(*x)));
+    let (bottom, top) = corners.iter().fold((f32::MAX, f32::MIN), |(lo, hi), (_, y)| (lo.min(*y), hi.max(*y)));
+    if right - left == 0.0 || top - bottom == 0.0 {
+        return None
+    }
+    let (x1, x2) = (rect[0].min(rect[2]), rect[0].max(rect[2]));
+    let (y1, y2) = (rect[1].min(rect[3]), rect[1].max(rect[3]));
+    let scale_x = (x2 - x1) / (right - left);
+    let scale_y = (y2 - y1) / (top - bottom);
+    Some([scale_x, 0.0, 0.0, scale_y, x1 - left * scale_x, y1 - bottom * scale_y])
+}
+
+/// Draws every visible widget into the content of its page and removes the form, so what was
+/// filled in becomes part of the page. Returns how many widgets were drawn.
+fn flatten(doc: &mut Document) -> usize {
+    let pages = doc.get_pages();
+    let mut drawn = 0;
+
+    for (number, ids) in annotations::page_annotations(doc) {
+        let page_id = match pages.get(&number) {
+            Some(page_id) => *page_id,
+            None => continue,
+        };
+        let mut widgets: BTreeSet<ObjectId> = BTreeSet::new();
+        let mut draws: Vec<(ObjectId, [f32; 6])> = Vec::new();
+        for id in ids {
+            let widget = match doc.get_dictionary(id) {
+                Ok(widget) if is_widget(widget) => widget,
+                _ => continue,
+            };
+            widgets.insert(id);
+            let hidden = widget.get(b"F").and_then(Object::as_i64).map(|flags| flags & NOT_DRAWN != 0).unwrap_or(false);
+            if hidden {
+                continue
+            }
+            let rect = numbers(widget.get(b"Rect").ok()).unwrap_or_default();
+            let placed = appearance(doc, widget)
+                .and_then(|ap_id| Some((ap_id, placement(doc.get_object(ap_id).and_then(Object::as_stream).ok()?, &rect)?)));
+            if let Some(placed) = placed {
+                draws.push(placed);
+            }
+        }
+        if widgets.is_empty() {
+            continue
+        }
+
+        if !draws.is_empty() {
+            // add_xobject would give a page that inherits its resources an empty dictionary of its own.
+            let resources = doc.get_dictionary(page_id).ok()
+                .filter(|page| !page.has(b"Resources"))
+                .and_then(|page| inherited(doc, page, b"Resources"))
+                .cloned();
+            if let (Some(resources), Ok(page)) = (resources, doc.get_dictionary_mut(page_id)) {
+                page.set("Resources", resources);
+            }
+
+            // The original content may leave the graphics state changed, so it goes between q and Q.
+            let mut content = b"Q\n".to_vec();
+            for (ap_id, [a, b, c, d, e, f]) in draws.iter() {
+                let name = format!("FlattenedField{}_{}", ap_id.0, ap_id.1);
+                if let Ok(appearance) = doc.get_object_mut(*ap_id).and_then(Object::as_stream_mut) {
+                    appearance.dict.set("Type", Object::Name(b"XObject".to_vec()));
+                    appearance.dict.set("Subtype", Object::Name(b"Form".to_vec()));
+                }
+                if doc.add_xobject(page_id, name.as_bytes(), *ap_id).is_ok() {
+                    content.extend(format!("q {} {} {} {} {} {} cm /{} Do Q\n", a, b, c, d, e, f, name).into_bytes());
+                    drawn += 1;
+                }
+            }
+            let save = doc.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec()));
+            let restore = doc.add_object(Stream::new(Dictionary::new(), content));
+            if let Ok(page) = doc.get_dictionary_mut(page_id) {
+                let mut contents: Vec<Object> = match page.get(b"Contents") {
+                    Ok(Object::Array(contents)) => contents.clone(),
+                    Ok(contents) => vec![contents.clone()],
+                    Err(_) => Vec::new(),
+                };
+                contents.insert(0, Object::Reference(save));
+                contents.push(Object::Reference(restore));
+                page.set("Contents", contents);
+            }
+        }
+        annotations::drop_from_page(doc, page_id, &widgets);
+    }
+
+    if let Ok(catalog) = doc.catalog_mut() {
+        catalog.remove(b"AcroForm");
+        catalog.remove(b"NeedsRendering");
+    }
+    drawn
+}
+
+/// Reports the form data of the document and applies `policy` to it. Returns report lines.
+pub(crate) fn clean(doc: &mut Document, policy: FormPolicy) -> Vec<String> {
+    let mut report: Vec<String> = Vec::new();
+    let fields = fields(doc);
+    let filled: Vec<&str> = fields.iter()
+        .filter(|(id, _)| doc.get_dictionary(*id).map(|field| VALUE_KEYS.iter().any(|key| field.has(key))).unwrap_or(false))
+        .map(|(_, name)| name.as_str())
+        .collect();
+    let xfa = xfa_packets(doc);
+
+    if !filled.is_empty() && !policy.clear {
+        report.push(format!("form fields with values: {}", filled.join(", ")));
+    }
+    if policy.clear && clear(doc, &fields) > 0 {
+        report.push(format!("cleared form fields: {}", filled.join(", ")));
+    }
+    match xfa {
+        Some(packets) if policy.remove_xfa && remove_xfa(doc) => {
+            if packets.iter().any(|packet| packet == "datasets") {
+                report.push("removed XFA datasets".to_string());
+            } else {
+                report.push("removed XFA form".to_string());
+            }
+        },
+        Some(packets) if packets.iter().any(|packet| packet == "datasets") && !policy.flatten => {
+            report.push("XFA datasets present".to_string());
+        },
+        _ => {}
+    }
+    if policy.flatten && !fields.is_empty() {
+        report.push(format!("flattened {} form fields into the page content", flatten(doc)));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::dictionary;
+    use crate::pdf::test_support::{self, CATALOG_ID, CONTENT_ID, PAGE_ID};
+    use super::*;
+
+    struct Form {
+        name: ObjectId,
+        agree: ObjectId,
+        name_ap: ObjectId,
+        agree_ap: ObjectId,
+    }
+
+    /// A page with a text field "person.Näme", its name in UTF-16BE, and a ticked check box
+    /// "agree", both filled in, and an XFA form with its datasets.
+    fn filled() -> (Document, Form) {
+        let mut doc = test_support::document();
+        let name_ap = doc.add_object(Stream::new(dictionary! { "BBox" => vec![0.into(), 0.into(), 50.into(), 10.into()] }, b"BT (Jane Doe) Tj ET".to_vec()));
+        let agree_ap = doc.add_object(Stream::new(dictionary! {
+            "BBox" => vec![0.into(), 0.into(), 10.into(), 10.into()],
+            "Matrix" => vec![0.into(), 1.into(), (-1).into(), 0.into(), 10.into(), 0.into()],
+        }, b"0 0 m 10 10 l S".to_vec()));
+        let off_ap = doc.add_object(Stream::new(dictionary! { "BBox" => vec![0.into(), 0.into(), 10.into(), 10.into()] }, Vec::new()));
+        let person = doc.new_object_id();
+        let name = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Widget",
+            "Parent" => person,
+            "P" => PAGE_ID,
+            "FT" => "Tx",
+            "T" => Object::string_literal(b"\xfe\xff\0N\0\xe4\0m\0e".to_vec()),
+            "V" => Object::string_literal("Jane Doe"),
+            "Rect" => vec![10.into(), 20.into(), 110.into(), 40.into()],
+            "AP" => dictionary! { "N" => name_ap },
+        });
+        doc.objects.insert(person, Object::Dictionary(dictionary! { "T" => Object::string_literal("person"), "Kids" => vec![name.into()] }));
+        let agree = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Widget",
+            "P" => PAGE_ID,
+            "FT" => "Btn",
+            "T" => Object::string_literal("agree"),
+            "V" => "Yes",
+            "AS" => "Yes",
+            "Rect" => vec![150.into(), 20.into(), 160.into(), 30.into()],
+            "AP" => dictionary! { "N" => dictionary! { "Yes" => agree_ap, "Off" => off_ap } },
+        });
+        let template = doc.add_object(Stream::new(dictionary! {}, b"<template/>".to_vec()));
+        let datasets = doc.add_object(Stream::new(dictionary! {}, b"<xfa:datasets>Jane Doe</xfa:datasets>".to_vec()));
+        doc.get_dictionary_mut(PAGE_ID).expect("page").set("Annots", vec![name.into(), agree.into()]);
+        doc.get_dictionary_mut(CATALOG_ID).expect("catalog").set("AcroForm", dictionary! {
+            "Fields" => vec![person.into(), agree.into()],
+            "XFA" => vec![Object::string_literal("template"), template.into(), Object::string_literal("datasets"), datasets.into()],
+        });
+        (doc, Form { name, agree, name_ap, agree_ap })
+    }
+
+    fn lines(report: &[&str]) -> Vec<String> {
+        report.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn values_are_reported_with_their_full_names() {
+        let (mut doc, _) = filled();
+        let report = clean(&mut doc, FormPolicy::default());
+        assert_eq!(report, lines(&["form fields with values: person.Näme, agree", "XFA datasets present"]));
+    }
+
+    #[test]
+    fn clearing_removes_values_and_stale_appearances() {
+        let (mut doc, form) = filled();
+        let report = clean(&mut doc, FormPolicy { clear: true, ..FormPolicy::default() });
+        assert_eq!(report, lines(&["cleared form fields: person.Näme, agree", "XFA datasets present"]));
+
+        let name = doc.get_dictionary(form.name).expect("name");
+        assert!(!name.has(b"V") && !name.has(b"AP"));
+        let agree = doc.get_dictionary(form.agree).expect("agree");
+        assert!(!agree.has(b"V"));
+        assert_eq!(agree.get(b"AS").and_then(Object::as_name).ok(), Some(b"Off".as_slice()));
+        assert_eq!(acroform(&doc).and_then(|form| form.get(b"NeedAppearances").ok()), Some(&Object::Boolean(true)));
+    }
+
+    #[test]
+    fn xfa_datasets_are_dropped() {
+        let (mut doc, _) = filled();
+        let report = clean(&mut doc, FormPolicy { clear: true, remove_xfa: true, ..FormPolicy::default() });
+        assert_eq!(report, lines(&["cleared form fields: person.Näme, agree", "removed XFA datasets"]));
+        assert_eq!(xfa_packets(&doc), Some(vec!["template".to_string()]));
+    }
+
+    #[test]
+    fn flattening_draws_appearances_into_the_page() {
+        let (mut doc, form) = filled();
+        let report = clean(&mut doc, FormPolicy { flatten: true, ..FormPolicy::default() });
+        assert_eq!(report, lines(&["form fields with values: person.Näme, agree", "flattened 2 form fields into the page content"]));
+
+        assert!(!doc.catalog().expect("catalog").has(b"AcroForm"));
+        assert!(annotations::page_annotations(&doc).into_iter().all(|(_, ids)| ids.is_empty()));
+        let page = doc.get_dictionary(PAGE_ID).expect("page");
+        let contents: Vec<ObjectId> = page.get(b"Contents").and_then(Object::as_array).expect("contents")
+            .iter().map(|content| content.as_reference().expect("reference")).collect();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1], CONTENT_ID);
+        let content = |id| doc.get_object(id).and_then(Object::as_stream).map(|strm| strm.content.clone()).expect("content");
+        assert_eq!(content(contents[0]), b"q\n".to_vec());
+        // The check box is turned a quarter by its own /Matrix, which Do applies; the turned box already fits.
+        let drawn = format!(
+            "Q\nq 2 0 0 2 10 20 cm /FlattenedField{}_0 Do Q\nq 1 0 0 1 150 20 cm /FlattenedField{}_0 Do Q\n",
+            form.name_ap.0, form.agree_ap.0);
+        assert_eq!(String::from_utf8(content(contents[2])).expect("utf-8"), drawn);
+
+        let xobjects = page.get(b"Resources").and_then(Object::as_dict).and_then(|resources| resources.get(b"XObject")).and_then(Object::as_dict).expect("xobjects");
+        let name = format!("FlattenedField{}_0", form.name_ap.0);
+        assert_eq!(xobjects.get(name.as_bytes()).and_then(Object::as_reference).ok(), Some(form.name_ap));
+        let appearance = doc.get_object(form.name_ap).and_then(Object::as_stream).expect("appearance");
+        assert_eq!(appearance.dict.get(b"Subtype").and_then(Object::as_name).ok(), Some(b"Form".as_slice()));
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, BTreeSet};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use crate::pdf::annotations;
+use crate::pdf::objects::{catalog_dict, catalog_dict_mut, text};
+
+/// Name trees in the catalog /Names dictionary that hold active or attached content.
+const REMOVED_NAME_TREES: [&[u8]; 2] = [b"EmbeddedFiles", b"JavaScript"];
+
+/// Keys of the leaves of a name tree.
+fn name_tree_keys(doc: &Document, node: &Object, visited: &mut BTreeSet<ObjectId>) -> Vec<String> {
+    if let Object::Reference(id) = node {
+        if !visited.insert(*id) {
+            return Vec::new()
+        }
+    }
+    let node = match doc.dereference(node).ok().and_then(|(_, node)| node.as_dict().ok()) {
+        Some(node) => node,
+        None => return Vec::new(),
+    };
+
+    let mut keys: Vec<String> = node.get(b"Names").and_then(Object::as_array)
+        .map(|names| names.iter().step_by(2).filter_map(|key| key.as_str().ok()).map(text).collect())
+        .unwrap_or_default();
+    if let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) {
+        for kid in kids {
+            keys.extend(name_tree_keys(doc, kid, visited));
+        }
+    }
+    keys
+}
+
+/// Actions that run code, start programs, or send or load data without the reader asking.
+/// URI actions are kept: they are ordinary links and only open when clicked.
+const REMOVED_ACTIONS: [&[u8]; 4] = [b"JavaScript", b"Launch", b"SubmitForm", b"ImportData"];
+
+fn action_type(action: &Dictionary) -> Option<&[u8]> {
+    action.get(b"S").and_then(Object::as_name).ok()
+}
+
+/// Index into `REMOVED_ACTIONS` of an action that has to go.
+fn removed_kind(action: &Dictionary) -> Option<usize> {
+    action_type(action).and_then(|kind| REMOVED_ACTIONS.iter().position(|removed| *removed == kind))
+}
+
+/// `removed_kind` of an action, inline or one of the indirect `removed` ones.
+fn removed_action(action: &Object, removed: &BTreeMap<ObjectId, usize>) -> Option<usize> {
+    match action {
+        Object::Dictionary(action) => removed_kind(action),
+        Object::Reference(id) => removed.get(id).copied(),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct ActionCounts {
+    additional: usize,
+    removed: [usize; REMOVED_ACTIONS.len()],
+    links: usize,
+}
+
+/// Removes /AA everywhere, and /A and /Next entries that lead to a removed action.
+/// A removed action goes with whatever is chained after it.
+fn drop_actions(object: &mut Object, removed: &BTreeMap<ObjectId, usize>, counts: &mut ActionCounts) {
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => &mut stream.dict,
+        Object::Array(array) => {
+            array.iter_mut().for_each(|item| drop_actions(item, removed, counts));
+            return
+        },
+        _ => return,
+    };
+
+    if dict.remove(b"AA").is_some() {
+        counts.additional += 1;
+    }
+    if let Some(kind) = dict.get(b"A").ok().and_then(|action| removed_action(action, removed)) {
+        dict.remove(b"A");
+        counts.removed[kind] += 1;
+    }
+    let next = match dict.get_mut(b"Next") {
+        Ok(Object::Array(chain)) => {
+            chain.retain(|action| match removed_action(action, removed) {
+                Some(kind) => {
+                    counts.removed[kind] += 1;
+                    false
+                },
+                None => true,
+            });
+            !chain.is_empty()
+        },
+        Ok(action) => match removed_action(action, removed) {
+            Some(kind) => {
+                counts.removed[kind] += 1;
+                false
+            },
+            None => true,
+        },
+        Err(_) => true,
+    };
+    if !next {
+        dict.remove(b"Next");
+    }
+    if action_type(dict) == Some(b"URI") {
+        counts.links += 1;
+    }
+    for (_, value) in dict.iter_mut() {
+        drop_actions(value, removed, counts);
+    }
+}
+
+/// "N what: a, b" for the report, without the colon when there are no names.
+fn listed(count: usize, what: &str, names: &[String]) -> String {
+    if names.is_empty() {
+        format!("removed {} {}", count, what)
+    } else {
+        format!("removed {} {}: {}", count, what, names.join(", "))
+    }
+}
+
+/// Removes embedded files, file attachment annotations, JavaScript and actions that run
+/// when the document or a page is opened. Returns a report line for everything removed.
+pub(crate) fn harden(doc: &mut Document) -> Vec<String> {
+    let mut report: Vec<String> = Vec::new();
+
+    let trees: Vec<(&[u8], Object)> = catalog_dict(doc, b"Names")
+        .map(|names| REMOVED_NAME_TREES.iter()
+            .filter_map(|tree| names.get(tree).ok().map(|root| (*tree, root.clone())))
+            .collect())
+        .unwrap_or_default();
+    for (tree, root) in trees {
+        let keys = name_tree_keys(doc, &root, &mut BTreeSet::new());
+        if let Some(names) = catalog_dict_mut(doc, b"Names") {
+            names.remove(tree);
+        }
+        // A tree whose leaves can't be resolved still counts as one.
+        let count = keys.len().max(1);
+        if tree == b"EmbeddedFiles" {
+            report.push(listed(count, "embedded file(s)", &keys));
+        } else {
+            report.push(listed(count, "document script(s)", &keys));
+        }
+    }
+
+    // PDF 2.0 associated files point at the same file specifications.
+    if let Ok(catalog) = doc.catalog_mut() {
+        if catalog.remove(b"AF").is_some() {
+            report.push("removed associated files".to_string());
+        }
+        if catalog.remove(b"OpenAction").is_some() {
+            report.push("removed OpenAction".to_string());
+        }
+    }
+
+    let attachments = annotations::remove_subtype(doc, b"FileAttachment");
+    if attachments > 0 {
+        report.push(format!("removed {} file attachment annotations", attachments));
+    }
+
+    let removed: BTreeMap<ObjectId, usize> = doc.objects.iter()
+        .filter_map(|(id, object)| object.as_dict().ok().and_then(removed_kind).map(|kind| (*id, kind)))
+        .collect();
+    let mut counts = ActionCounts::default();
+    for object in doc.objects.values_mut() {
+        drop_actions(object, &removed, &mut counts);
+    }
+    if counts.additional > 0 {
+        report.push(format!("removed {} additional actions (/AA)", counts.additional));
+    }
+    for (kind, count) in REMOVED_ACTIONS.iter().zip(counts.removed) {
+        if count > 0 {
+            report.push(format!("removed {} {} actions", count, String::from_utf8_lossy(kind)));
+        }
+    }
+    if counts.links > 0 {
+        report.push(format!("kept {} URI links", counts.links));
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::dictionary;
+    use super::*;
+
+    fn action(doc: &Document, id: ObjectId) -> &Dictionary {
+        doc.get_dictionary(id).expect("action")
+    }
+
+    #[test]
+    fn removes_actions_chained_after_harmless_ones() {
+        let mut doc = Document::with_version("1.7");
+        let script = doc.add_object(dictionary! { "S" => "JavaScript", "JS" => Object::string_literal("app.alert(1)") });
+        let chained = doc.add_object(dictionary! {
+            "S" => "GoTo",
+            "D" => vec![Object::Integer(0)],
+            "Next" => vec![script.into(), Object::Dictionary(dictionary! { "S" => "URI", "URI" => Object::string_literal("https://example.com") })],
+        });
+        let single = doc.add_object(dictionary! {
+            "S" => "GoTo",
+            "D" => vec![Object::Integer(0)],
+            "Next" => dictionary! { "S" => "Launch", "F" => Object::string_literal("calc.exe") },
+        });
+        let direct = doc.add_object(dictionary! { "Subtype" => "Link", "A" => dictionary! { "S" => "SubmitForm" } });
+        let catalog = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Names" => dictionary! { "EmbeddedFiles" => dictionary! { "Kids" => vec![Object::Reference((99, 0))] } },
+        });
+        doc.trailer.set("Root", catalog);
+
+        let report = harden(&mut doc);
+
+        let next = action(&doc, chained).get(b"Next").and_then(Object::as_array).expect("harmless part of the chain kept");
+        assert_eq!(next.len(), 1);
+        assert!(action(&doc, single).get(b"Next").is_err());
+        assert!(action(&doc, direct).get(b"A").is_err());
+        assert_eq!(report, vec![
+            "removed 1 embedded file(s)".to_string(),
+            "removed 1 JavaScript actions".to_string(),
+            "removed 1 Launch actions".to_string(),
+            "removed 1 SubmitForm actions".to_string(),
+            "kept 1 URI links".to_string(),
+        ]);
+    }
+}
@@ -0,0 +1,105 @@
+use lopdf::{Document, Object, StringFormat};
+use crate::errors::error::PurgeErr;
+
+/// What happens to the trailer `/ID`, which otherwise ties the cleaned file to the
+/// machine and session that produced the original.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub(crate) enum IdMode {
+    #[default]
+    Keep,
+    Remove,
+    /// A fresh random ID on every run.
+    Random,
+    /// MD5 of the document saved without an ID: the same input always gives the same output.
+    Hash,
+}
+
+impl IdMode {
+    pub(crate) fn parse(value: &str) -> Option<IdMode> {
+        match value {
+            "keep" => Some(IdMode::Keep),
+            "remove" => Some(IdMode::Remove),
+            "random" => Some(IdMode::Random),
+            "hash" => Some(IdMode::Hash),
+            _ => None,
+        }
+    }
+}
+
+fn id_string(bytes: &[u8]) -> Object {
+    Object::String(bytes.to_vec(), StringFormat::Hexadecimal)
+}
+
+/// Applies `mode` to the trailer `/ID`. Has to run last, once the content it hashes is final.
+pub(crate) fn apply(doc: &mut Document, mode: IdMode) -> Result<(), PurgeErr> {
+    let id = match mode {
+        IdMode::Keep => return Ok(()),
+        IdMode::Remove => {
+            doc.trailer.remove(b"ID");
+            return Ok(())
+        },
+        IdMode::Random => {
+            let mut id = [0u8; 16];
+            getrandom::getrandom(&mut id).map_err(std::io::Error::from)?;
+            id
+        },
+        IdMode::Hash => {
+            doc.trailer.remove(b"ID");
+            // Saving numbers the cross-reference stream and fills the trailer in; that is left to the real save.
+            let (trailer, max_id) = (doc.trailer.clone(), doc.max_id);
+            let mut hash = md5::Context::new();
+            let saved = doc.save_to(&mut hash);
+            doc.trailer = trailer;
+            doc.max_id = max_id;
+            saved?;
+            hash.compute().0
+        },
+    };
+    // Both halves are the same: to a reader this is a new file, not a revision of one.
+    doc.trailer.set("ID", Object::Array(vec![id_string(&id), id_string(&id)]));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pdf::test_support::{self, saved};
+    use super::*;
+
+    fn id_after(mode: IdMode) -> Option<Vec<Vec<u8>>> {
+        let mut doc = test_support::document();
+        doc.trailer.set("ID", vec![id_string(b"original"), id_string(b"original")]);
+        apply(&mut doc, mode).expect("applies");
+        let id = doc.trailer.get(b"ID").and_then(Object::as_array).ok()?;
+        Some(id.iter().map(|half| half.as_str().expect("string").to_vec()).collect())
+    }
+
+    #[test]
+    fn hashing_gives_the_same_id_every_time() {
+        let id = id_after(IdMode::Hash).expect("has an ID");
+        assert_eq!(id_after(IdMode::Hash), Some(id.clone()));
+        assert_eq!(id[0].len(), 16);
+        assert_eq!(id[0], id[1]);
+    }
+
+    #[test]
+    fn hashing_leaves_the_document_to_the_real_save() {
+        let mut hashed = test_support::document();
+        apply(&mut hashed, IdMode::Hash).expect("applies");
+        assert_eq!(hashed.max_id, test_support::document().max_id);
+        assert!(!hashed.trailer.has(b"Type") && !hashed.trailer.has(b"Size"));
+        assert!(lopdf::Document::load_mem(&saved(&mut hashed)).expect("loads").trailer.has(b"ID"));
+    }
+
+    #[test]
+    fn removing_deletes_the_id() {
+        assert_eq!(id_after(IdMode::Remove), None);
+        assert_eq!(id_after(IdMode::Keep), Some(vec![b"original".to_vec(), b"original".to_vec()]));
+    }
+
+    #[test]
+    fn random_ids_differ() {
+        let (first, second) = (id_after(IdMode::Random).expect("has an ID"), id_after(IdMode::Random).expect("has an ID"));
+        assert!(first[0].len() == 16 && second[0].len() == 16);
+        assert_ne!(first, second);
+    }
+}
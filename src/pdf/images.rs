@@ -0,0 +1,103 @@
+use lopdf::{Document, Object, Stream};
+use crate::jpeg::Cleaner;
+
+/// An image XObject stored as a plain JPEG: /Filter /DCTDecode, alone or as the only array item.
+/// A JPEG under another filter would have to be decoded and encoded again, so those are left alone.
+fn is_jpeg_image(strm: &Stream) -> bool {
+    let is_image = strm.dict.get(b"Subtype").and_then(Object::as_name).map(|subtype| subtype == b"Image").unwrap_or(false);
+    let is_dct = match strm.dict.get(b"Filter") {
+        Ok(Object::Name(filter)) => filter == b"DCTDecode",
+        Ok(Object::Array(filters)) => matches!(filters.as_slice(), [Object::Name(filter)] if filter == b"DCTDecode"),
+        _ => false,
+    };
+    is_image && is_dct
+}
+
+/// Runs every embedded JPEG through the same cleaning as a JPEG file. Returns report lines.
+///
+/// Images the cleaner can't parse are left as they are and counted, instead of failing the whole document.
+pub(crate) fn clean_jpegs(doc: &mut Document, cleaner: &Cleaner) -> Vec<String> {
+    let mut cleaned = 0;
+    let mut skipped = 0;
+    let mut removed: Vec<String> = Vec::new();
+
+    for object in doc.objects.values_mut() {
+        let strm = match object.as_stream_mut() {
+            Ok(strm) if is_jpeg_image(strm) => strm,
+            _ => continue,
+        };
+        match cleaner.clean(&strm.content) {
+            Ok((content, report)) => {
+                if content != strm.content {
+                    // set_content keeps the filter and updates /Length.
+                    strm.set_content(content);
+                    cleaned += 1;
+                }
+                for line in report {
+                    if !removed.contains(&line) {
+                        removed.push(line);
+                    }
+                }
+            },
+            Err(_) => skipped += 1,
+        }
+    }
+
+    let mut report: Vec<String> = Vec::new();
+    if cleaned > 0 {
+        report.push(format!("cleaned {} embedded JPEG images: {}", cleaned, removed.join("; ")));
+    }
+    if skipped > 0 {
+        report.push(format!("left {} embedded JPEG images that could not be parsed", skipped));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::dictionary;
+    use crate::pdf::test_support;
+    use super::*;
+
+    /// SOI, an APP1 with an (empty) EXIF block, EOI.
+    fn jpeg_with_exif() -> Vec<u8> {
+        let exif = b"Exif\0\0II*\0\x08\0\0\0\0\0\0\0";
+        let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe1];
+        jpeg.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        jpeg.extend_from_slice(exif);
+        jpeg.extend_from_slice(&[0xff, 0xd9]);
+        jpeg
+    }
+
+    fn image(filter: Object, content: Vec<u8>) -> Stream {
+        Stream::new(dictionary! { "Type" => "XObject", "Subtype" => "Image", "Filter" => filter }, content)
+    }
+
+    #[test]
+    fn exif_is_stripped_from_embedded_jpegs() {
+        let mut doc = test_support::document();
+        let plain = doc.add_object(image("DCTDecode".into(), jpeg_with_exif()));
+        let listed = doc.add_object(image(vec!["DCTDecode".into()].into(), jpeg_with_exif()));
+        let report = clean_jpegs(&mut doc, &Cleaner::from_options());
+
+        assert_eq!(report, vec!["cleaned 2 embedded JPEG images: removed APP segments: Exif".to_string()]);
+        for id in [plain, listed] {
+            let strm = doc.get_object(id).and_then(Object::as_stream).expect("image");
+            assert_eq!(strm.content, vec![0xff, 0xd8, 0xff, 0xd9]);
+            assert_eq!(strm.dict.get(b"Length").and_then(Object::as_i64).ok(), Some(4));
+        }
+    }
+
+    #[test]
+    fn other_filters_and_broken_images_are_left_alone() {
+        let mut doc = test_support::document();
+        let chained = doc.add_object(image(vec!["FlateDecode".into(), "DCTDecode".into()].into(), jpeg_with_exif()));
+        let broken = doc.add_object(image("DCTDecode".into(), b"not a jpeg".to_vec()));
+        let report = clean_jpegs(&mut doc, &Cleaner::from_options());
+
+        assert_eq!(report, vec!["left 1 embedded JPEG images that could not be parsed".to_string()]);
+        let content = |id| doc.get_object(id).and_then(Object::as_stream).map(|strm| strm.content.clone()).expect("image");
+        assert_eq!(content(chained), jpeg_with_exif());
+        assert_eq!(content(broken), b"not a jpeg".to_vec());
+    }
+}
@@ -0,0 +1,52 @@
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+/// lopdf can't linearize (fast web view), so that is left to qpdf, run on the saved file.
+const QPDF: &str = "qpdf";
+
+/// qpdf exits with 3 when it succeeded with warnings.
+const QPDF_WARNINGS: i32 = 3;
+
+/// Whether qpdf can be run at all.
+pub(crate) fn available() -> bool {
+    Command::new(QPDF)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// Linearizes the PDF at `path` in place. Encrypted files stay encrypted the same way; the
+/// password goes through stdin rather than the command line.
+///
+/// qpdf writes a trailer ID of its own. It keeps the first half of the one in the file and makes
+/// up the second, unless `deterministic_id` asks for one derived from the content, as `--pdf-id=hash` does.
+pub(crate) fn linearize(path: &str, password: Option<&str>, deterministic_id: bool) -> Result<(), String> {
+    let mut command = Command::new(QPDF);
+    command.arg("--linearize").arg("--replace-input");
+    if deterministic_id {
+        command.arg("--deterministic-id");
+    }
+    if password.is_some() {
+        command.arg("--password-file=-");
+    }
+    let mut qpdf = command.arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => "qpdf isn't installed".to_string(),
+            _ => err.to_string(),
+        })?;
+    if let (Some(password), Some(mut stdin)) = (password, qpdf.stdin.take()) {
+        stdin.write_all(password.as_bytes()).map_err(|err| err.to_string())?;
+    }
+    let output = qpdf.wait_with_output().map_err(|err| err.to_string())?;
+    match output.status.code() {
+        Some(0) | Some(QPDF_WARNINGS) => Ok(()),
+        _ => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
@@ -0,0 +1,41 @@
+use lopdf::{Dictionary, Document, Object};
+
+/// A PDF text string for the report: UTF-16BE with a byte order mark, or PDFDocEncoding read as Latin-1.
+pub(crate) fn text(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xfe, 0xff]) {
+        Some(utf16) => String::from_utf16_lossy(
+            &utf16.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect::<Vec<u16>>()),
+        None => bytes.iter().map(|byte| char::from(*byte)).collect(),
+    }
+}
+
+/// The catalog dictionary under `key`, whether it is direct or an object of its own.
+pub(crate) fn catalog_dict<'a>(doc: &'a Document, key: &[u8]) -> Option<&'a Dictionary> {
+    let entry = doc.catalog().and_then(|catalog| catalog.get(key)).ok()?;
+    doc.dereference(entry).ok()?.1.as_dict().ok()
+}
+
+/// `catalog_dict`, to change it in place.
+pub(crate) fn catalog_dict_mut<'a>(doc: &'a mut Document, key: &[u8]) -> Option<&'a mut Dictionary> {
+    let id = match doc.catalog().and_then(|catalog| catalog.get(key)) {
+        Ok(Object::Reference(id)) => Some(*id),
+        Ok(Object::Dictionary(_)) => None,
+        _ => return None,
+    };
+    match id {
+        Some(id) => doc.get_dictionary_mut(id).ok(),
+        None => doc.catalog_mut().and_then(|catalog| catalog.get_mut(key)).and_then(Object::as_dict_mut).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_strings_are_decoded() {
+        assert_eq!(text(b"Name"), "Name");
+        assert_eq!(text(b"Caf\xe9"), "Café");
+        assert_eq!(text(b"\xfe\xff\x00N\x00\xe4\x00m\x00e"), "Näme");
+    }
+}
@@ -0,0 +1,33 @@
+use std::collections::BTreeSet;
+use lopdf::{Document, Object, ObjectId};
+use crate::pdf::metadata_keys;
+
+/// Removes document-information keys some producers copy onto outline items.
+/// /Title stays: it is the bookmark text.
+pub(crate) fn scrub_metadata(doc: &mut Document) {
+    let root = doc.catalog().ok()
+        .and_then(|catalog| catalog.get(b"Outlines").ok())
+        .and_then(|outlines| outlines.as_reference().ok());
+
+    let mut pending: Vec<ObjectId> = root.into_iter().collect();
+    let mut visited: BTreeSet<ObjectId> = BTreeSet::new();
+    while let Some(id) = pending.pop() {
+        if !visited.insert(id) {
+            continue
+        }
+        let item = match doc.get_dictionary_mut(id) {
+            Ok(item) => item,
+            Err(_) => continue,
+        };
+        for key in metadata_keys(item) {
+            if key != b"Title" {
+                item.remove(&key);
+            }
+        }
+        for link in [b"First".as_slice(), b"Next".as_slice()] {
+            if let Ok(next) = item.get(link).and_then(Object::as_reference) {
+                pending.push(next);
+            }
+        }
+    }
+}
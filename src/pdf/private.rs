@@ -0,0 +1,94 @@
+use lopdf::{Dictionary, Document, Object};
+
+/// Keys under which applications keep their own data: /PieceInfo holds one dictionary per
+/// application (Illustrator's points at its AIPrivateData streams), /LastModified dates it.
+const PRIVATE_KEYS: [&[u8]; 2] = [b"PieceInfo", b"LastModified"];
+
+fn is_one_of(dict: &Dictionary, key: &[u8], names: &[&[u8]]) -> bool {
+    dict.get(key).and_then(Object::as_name).map(|name| names.contains(&name)).unwrap_or(false)
+}
+
+/// Where the PDF specification allows page-piece dictionaries: the catalog, pages and form XObjects.
+fn holds_private_data(object: &Object) -> Option<&Dictionary> {
+    match object {
+        Object::Dictionary(dict) if is_one_of(dict, b"Type", &[b"Catalog", b"Pages", b"Page"]) => Some(dict),
+        Object::Stream(strm) if is_one_of(&strm.dict, b"Subtype", &[b"Form"]) => Some(&strm.dict),
+        _ => None,
+    }
+}
+
+/// Removes application private data from the catalog, pages and form XObjects.
+/// What it pointed at (AIPrivateData streams and the like) is left for `prune_objects`.
+/// Returns a report line naming the applications, if there was any.
+pub(crate) fn remove_application_data(doc: &mut Document) -> Option<String> {
+    let ids: Vec<_> = doc.objects.iter()
+        .filter(|(_, object)| holds_private_data(object)
+            .map(|dict| PRIVATE_KEYS.iter().any(|key| dict.has(key)))
+            .unwrap_or(false))
+        .map(|(id, _)| *id)
+        .collect();
+
+    let mut applications: Vec<String> = Vec::new();
+    for id in ids.iter() {
+        let dict = match doc.objects.get_mut(id) {
+            Some(Object::Dictionary(dict)) => dict,
+            Some(Object::Stream(strm)) => &mut strm.dict,
+            _ => continue,
+        };
+        if let Some(Object::Dictionary(pieces)) = dict.remove(b"PieceInfo") {
+            for (application, _) in pieces.iter() {
+                let application = String::from_utf8_lossy(application).into_owned();
+                if !applications.contains(&application) {
+                    applications.push(application);
+                }
+            }
+        }
+        dict.remove(b"LastModified");
+    }
+
+    if ids.is_empty() {
+        return None
+    }
+    if applications.is_empty() {
+        Some(format!("removed application private data from {} objects", ids.len()))
+    } else {
+        Some(format!("removed application private data from {} objects ({})", ids.len(), applications.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::{dictionary, Stream};
+    use crate::pdf::test_support::{self, CATALOG_ID, INFO_ID, PAGE_ID};
+    use super::*;
+
+    fn pieces() -> Dictionary {
+        dictionary! { "Illustrator" => dictionary! { "Private" => Object::Reference((9, 0)) } }
+    }
+
+    #[test]
+    fn piece_info_is_removed_from_pages_and_forms() {
+        let mut doc = test_support::document();
+        doc.get_dictionary_mut(PAGE_ID).expect("page").set("PieceInfo", pieces());
+        doc.get_dictionary_mut(CATALOG_ID).expect("catalog").set("LastModified", Object::string_literal("D:20240101"));
+        let form = doc.add_object(Stream::new(dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "PieceInfo" => dictionary! { "Photoshop" => dictionary! {} },
+        }, Vec::new()));
+        // Not a place for page-piece dictionaries, left alone.
+        doc.get_dictionary_mut(INFO_ID).expect("info").set("PieceInfo", pieces());
+
+        let report = remove_application_data(&mut doc);
+        assert_eq!(report.as_deref(), Some("removed application private data from 3 objects (Illustrator, Photoshop)"));
+        assert!(!doc.get_dictionary(PAGE_ID).expect("page").has(b"PieceInfo"));
+        assert!(!doc.get_dictionary(CATALOG_ID).expect("catalog").has(b"LastModified"));
+        assert!(!doc.get_object(form).and_then(Object::as_stream).expect("form").dict.has(b"PieceInfo"));
+        assert!(doc.get_dictionary(INFO_ID).expect("info").has(b"PieceInfo"));
+    }
+
+    #[test]
+    fn clean_documents_are_not_reported() {
+        assert_eq!(remove_application_data(&mut test_support::document()), None);
+    }
+}
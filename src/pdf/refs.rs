@@ -0,0 +1,82 @@
+use std::collections::BTreeSet;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+/// Removes references to objects that are no longer in the document, starting with the
+/// trailer (`/Info`) and the catalog (`/Metadata`). Dictionary entries are removed and
+/// array items become null, which is how readers treat a missing object anyway.
+/// Returns how many references were dropped.
+pub(crate) fn drop_dangling(doc: &mut Document) -> usize {
+    let existing: BTreeSet<ObjectId> = doc.objects.keys().copied().collect();
+    let mut dropped = drop_in_dict(&mut doc.trailer, &existing);
+    for object in doc.objects.values_mut() {
+        dropped += drop_in_object(object, &existing);
+    }
+    dropped
+}
+
+fn is_dangling(object: &Object, existing: &BTreeSet<ObjectId>) -> bool {
+    matches!(object, Object::Reference(id) if !existing.contains(id))
+}
+
+fn drop_in_object(object: &mut Object, existing: &BTreeSet<ObjectId>) -> usize {
+    match object {
+        Object::Dictionary(dict) => drop_in_dict(dict, existing),
+        Object::Stream(stream) => drop_in_dict(&mut stream.dict, existing),
+        Object::Array(array) => {
+            let mut dropped = 0;
+            for item in array.iter_mut() {
+                if is_dangling(item, existing) {
+                    *item = Object::Null;
+                    dropped += 1;
+                } else {
+                    dropped += drop_in_object(item, existing);
+                }
+            }
+            dropped
+        },
+        _ => 0,
+    }
+}
+
+fn drop_in_dict(dict: &mut Dictionary, existing: &BTreeSet<ObjectId>) -> usize {
+    let dangling: Vec<Vec<u8>> = dict.iter()
+        .filter(|(_, value)| is_dangling(value, existing))
+        .map(|(key, _)| key.clone())
+        .collect();
+    for key in dangling.iter() {
+        dict.remove(key);
+    }
+
+    let mut dropped = dangling.len();
+    for (_, value) in dict.iter_mut() {
+        dropped += drop_in_object(value, existing);
+    }
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::dictionary;
+    use crate::pdf::test_support::{self, CATALOG_ID, INFO_ID, PAGE_ID};
+    use super::*;
+
+    #[test]
+    fn references_to_removed_objects_are_dropped() {
+        let mut doc = test_support::document();
+        let metadata_id = doc.add_object(dictionary! { "Type" => "Metadata" });
+        doc.get_dictionary_mut(CATALOG_ID).expect("catalog").set("Metadata", metadata_id);
+        let popup_id = doc.add_object(dictionary! { "Subtype" => "Popup" });
+        doc.get_dictionary_mut(PAGE_ID).expect("page").set("Annots", vec![popup_id.into(), Object::Integer(1)]);
+        doc.objects.remove(&INFO_ID);
+        doc.objects.remove(&metadata_id);
+        doc.objects.remove(&popup_id);
+
+        assert_eq!(drop_dangling(&mut doc), 3);
+        assert!(!doc.trailer.has(b"Info") && doc.trailer.has(b"Root"));
+        let catalog = doc.get_dictionary(CATALOG_ID).expect("catalog");
+        assert!(!catalog.has(b"Metadata") && catalog.has(b"Pages"));
+        let annots = doc.get_dictionary(PAGE_ID).and_then(|page| page.get(b"Annots")).and_then(Object::as_array).expect("annots");
+        assert_eq!(annots, &vec![Object::Null, Object::Integer(1)]);
+        assert_eq!(drop_dangling(&mut doc), 0);
+    }
+}
@@ -0,0 +1,129 @@
+use lopdf::{Document, Object};
+use crate::errors::error::{PdfStructureErr, PurgeErr};
+
+const EOF_MARKER: &[u8] = b"%%EOF";
+
+/// `%%EOF` markers that start a line, i.e. ends of a revision rather than text that happens to contain one.
+fn eof_markers(bytes: &[u8]) -> usize {
+    bytes.windows(EOF_MARKER.len())
+        .enumerate()
+        .filter(|(at, window)| *window == EOF_MARKER && (*at == 0 || matches!(bytes[at - 1], b'\n' | b'\r')))
+        .count()
+}
+
+/// Markers inside stream data, e.g. an embedded PDF, which don't end a revision of this file.
+fn markers_in_streams(doc: &Document) -> usize {
+    doc.objects.values()
+        .filter_map(|object| object.as_stream().ok())
+        .map(|strm| eof_markers(&strm.content))
+        .sum()
+}
+
+pub(crate) fn is_linearized(doc: &Document) -> bool {
+    doc.objects.values().any(|object| matches!(object, Object::Dictionary(dict) if dict.has(b"Linearized")))
+}
+
+/// How many revisions `bytes`, loaded as `doc`, is made of.
+///
+/// Every incremental save appends an update ending in its own `%%EOF`. A linearized file
+/// has one more for its first page section, which isn't history.
+pub(crate) fn count(bytes: &[u8], doc: &Document) -> usize {
+    let markers = eof_markers(bytes).saturating_sub(markers_in_streams(doc));
+    if is_linearized(doc) {
+        markers.saturating_sub(1)
+    } else {
+        markers
+    }
+}
+
+/// Makes sure `saved` is a single revision ending with its only `%%EOF`, so nothing from the
+/// original file can be recovered from earlier xref sections.
+pub(crate) fn verify_single(saved: &[u8], doc: &Document) -> Result<(), PurgeErr> {
+    let ends_with_eof = saved.trim_ascii_end().ends_with(EOF_MARKER);
+    if !ends_with_eof || eof_markers(saved).saturating_sub(markers_in_streams(doc)) != 1 {
+        return Err(PurgeErr::from(PdfStructureErr::new("rewritten file still has more than one revision")))
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::{dictionary, Stream};
+    use crate::pdf::test_support::{self, saved};
+    use super::*;
+
+    /// The shared one page document, optionally with a stream that embeds something looking like the end of a file.
+    fn document(embedded_eof: bool) -> Document {
+        let mut doc = test_support::document();
+        if embedded_eof {
+            doc.add_object(Stream::new(dictionary! {}, b"%PDF-1.4\n%%EOF\n".to_vec()));
+        }
+        doc
+    }
+
+    /// Appends an incremental update adding one string object, as an editor saving in place would.
+    fn append_update(bytes: &mut Vec<u8>, doc: &Document) {
+        let startxref = bytes.windows(b"startxref".len())
+            .rposition(|window| window == b"startxref")
+            .expect("startxref");
+        let prev: String = String::from_utf8_lossy(&bytes[startxref + b"startxref".len()..])
+            .trim_start()
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect();
+        let root = doc.trailer.get(b"Root").and_then(Object::as_reference).expect("root");
+        let id = doc.max_id + 1;
+
+        let object_at = bytes.len();
+        bytes.extend_from_slice(format!("{} 0 obj\n(update)\nendobj\n", id).as_bytes());
+        let xref_at = bytes.len();
+        bytes.extend_from_slice(format!(
+            "xref\n0 1\n0000000000 65535 f \n{} 1\n{:010} 00000 n \ntrailer\n<< /Size {} /Root {} {} R /Prev {} >>\nstartxref\n{}\n%%EOF\n",
+            id, object_at, id + 1, root.0, root.1, prev, xref_at,
+        ).as_bytes());
+    }
+
+    #[test]
+    fn a_saved_file_is_one_revision() {
+        let mut doc = document(false);
+        let bytes = saved(&mut doc);
+        let loaded = Document::load_mem(&bytes).expect("loads");
+        assert_eq!(count(&bytes, &loaded), 1);
+        assert!(verify_single(&bytes, &loaded).is_ok());
+    }
+
+    #[test]
+    fn incremental_updates_are_counted() {
+        let mut doc = document(false);
+        let mut bytes = saved(&mut doc);
+        append_update(&mut bytes, &doc);
+        let loaded = Document::load_mem(&bytes).expect("updated file loads");
+        assert_eq!(count(&bytes, &loaded), 2);
+        assert!(verify_single(&bytes, &loaded).is_err());
+    }
+
+    #[test]
+    fn markers_in_streams_and_linearization_are_not_revisions() {
+        let mut doc = document(true);
+        let bytes = saved(&mut doc);
+        let loaded = Document::load_mem(&bytes).expect("loads");
+        assert_eq!(eof_markers(&bytes), 2);
+        assert_eq!(count(&bytes, &loaded), 1);
+        assert!(verify_single(&bytes, &loaded).is_ok());
+
+        // lopdf doesn't write linearization dictionaries, so the first page section is made up here.
+        let mut linearized = document(false);
+        linearized.add_object(dictionary! { "Linearized" => 1 });
+        assert!(is_linearized(&linearized));
+        let two_sections = b"%PDF-1.4\n1 0 obj\n<< /Linearized 1 >>\nendobj\n%%EOF\n%%EOF\n";
+        assert_eq!(count(two_sections, &linearized), 1);
+        assert_eq!(count(two_sections, &document(false)), 2);
+    }
+
+    #[test]
+    fn only_markers_starting_a_line_count() {
+        assert_eq!(eof_markers(b"%%EOF"), 1);
+        assert_eq!(eof_markers(b"(text %%EOF)\n%%EOF\r%%EOF"), 2);
+        assert!(verify_single(b"%PDF-1.4\n%%EOF\ntrailing", &Document::new()).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use lopdf::encryption::crypt_filters::{Aes256CryptFilter, CryptFilter};
+use lopdf::{dictionary, Document, EncryptionState, EncryptionVersion, Object, ObjectId, Permissions, Stream};
+
+pub(crate) const TITLE: &[u8] = b"a secret title";
+pub(crate) const CONTENT: &[u8] = b"BT (a secret text) Tj ET";
+
+pub(crate) const PAGES_ID: ObjectId = (1, 0);
+pub(crate) const CONTENT_ID: ObjectId = (2, 0);
+pub(crate) const PAGE_ID: ObjectId = (3, 0);
+pub(crate) const CATALOG_ID: ObjectId = (4, 0);
+pub(crate) const INFO_ID: ObjectId = (5, 0);
+
+/// A one page document with a content stream and a title in the trailer /Info.
+pub(crate) fn document() -> Document {
+    let mut doc = Document::with_version("1.5");
+    doc.objects.insert(PAGES_ID, Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![PAGE_ID.into()],
+        "Count" => 1,
+    }));
+    doc.objects.insert(CONTENT_ID, Object::Stream(Stream::new(dictionary! {}, CONTENT.to_vec())));
+    doc.objects.insert(PAGE_ID, Object::Dictionary(dictionary! {
+        "Type" => "Page",
+        "Parent" => PAGES_ID,
+        "MediaBox" => vec![0.into(), 0.into(), 200.into(), 200.into()],
+        "Contents" => CONTENT_ID,
+    }));
+    doc.objects.insert(CATALOG_ID, Object::Dictionary(dictionary! { "Type" => "Catalog", "Pages" => PAGES_ID }));
+    doc.objects.insert(INFO_ID, Object::Dictionary(dictionary! { "Title" => Object::string_literal(TITLE) }));
+    doc.max_id = INFO_ID.0;
+    doc.trailer.set("Root", CATALOG_ID);
+    doc.trailer.set("Info", INFO_ID);
+    doc
+}
+
+pub(crate) fn saved(doc: &mut Document) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes).expect("saves");
+    bytes
+}
+
+pub(crate) fn contains(bytes: &[u8], needle: &[u8]) -> bool {
+    bytes.windows(needle.len()).any(|window| window == needle)
+}
+
+/// `doc` saved with AES-256 encryption, printable but not modifiable, owner password "owner".
+pub(crate) fn aes_encrypted(mut doc: Document, user_password: &str) -> Vec<u8> {
+    let crypt_filter: Arc<dyn CryptFilter> = Arc::new(Aes256CryptFilter);
+    let state = EncryptionState::try_from(EncryptionVersion::V5 {
+        encrypt_metadata: true,
+        crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), crypt_filter)]),
+        file_encryption_key: &[7u8; 32],
+        stream_filter: b"StdCF".to_vec(),
+        string_filter: b"StdCF".to_vec(),
+        owner_password: "owner",
+        user_password,
+        permissions: Permissions::PRINTABLE,
+    }).expect("valid encryption");
+    doc.encrypt(&state).expect("encrypts");
+    saved(&mut doc)
+}
@@ -1,106 +1,21 @@
+mod chunks;
+mod ancillary;
+mod apng;
+
 use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
-use crate::errors::error::{ExifStructureErr, PurgeErr};
+use crate::errors::error::PurgeErr;
+use crate::options::OPTIONS;
+use crate::png::ancillary::{ChunkClass, ChunkPolicy};
+use crate::png::apng::Animation;
 use crate::traits::container::{DataPaths, Heaped};
 
-
-#[derive(Debug, Copy, Clone)]
-struct Range {
-    start: u16,
-    end: u16
-}
-
-impl Range {
-    fn new(start: u16, end: u16) -> Self {
-        Range {
-            start,
-            end,
-        }
-    }
-}
-
-fn dont_take_ranges(src: &Vec<u8>, ranges: Vec<Range>) -> Vec<u8> {
-
-    let mut src_iter = src.iter().enumerate();
-    let mut rg_iter = ranges.iter();
-
-    let mut clean_buf: Vec<u8> = Vec::new();
-
-    while let Some(range) = rg_iter.next() {
-        let start = range.start;
-        let end = range.end;
-        while let Some((index, data)) = src_iter.next() {
-            if index < start as usize {
-                clean_buf.push(data.clone())
-            } else if index == end as usize {
-                {
-                    break
-                }
-            }
-        }
-
-    };
-    while let Some ((_, data)) = src_iter.next() {
-        clean_buf.push(data.clone())
-    };
-
-    clean_buf
-}
-
-fn get_ancil_ranges(src: &Vec<u8>) -> Result<Vec<Range>,PurgeErr> {
-
-    let mut anxil_ranges: Vec<Range> = Vec::new();
-    if let Some(data) = src.get(..=7) {
-        if data != [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A] {
-            return Err(PurgeErr::from(ExifStructureErr::new("not png")))
-        }
-    }
-
-    let mut vec_iter = src.windows(8).enumerate();
-
-    if let None =  vec_iter.nth(8) {
-        return Err(PurgeErr::from(ExifStructureErr::new("empty png")))
-    }
-
-    while let Some((index, bytes)) = vec_iter.next() {
-        match bytes {
-            chunk @ [_,_,_,_,116, 69, 88, 116] // tEXt
-            | chunk @ [_,_,_,_,0x69, 0x54, 0x58, 0x74] //iTXt
-            => {
-                let length = chunk.get(0..=3).unwrap();
-
-                let d_len = u32::from_be_bytes(
-                    [length[0],
-                        length[1],
-                        length[2],
-                        length[3]]) as usize;
-
-                // println!("d_len is: {:?}", d_len);
-
-                let start = index;
-
-                // 11 accounts for length bytes, type bytes and CNC.
-                // Unfortunately I do NOT know why it's 11 instead of supposed 12.
-                let end = index
-                    + d_len
-                    + 11;
-
-                // println!("tEXt found at: [{} - {}]", &start, &end);
-                anxil_ranges.push(Range::new(start as u16, end as u16))
-            },
-            _ => {}
-        }
-
-    }
-
-    Ok(anxil_ranges)
-}
-
-
 pub(crate) struct Png {
     pub(crate) paths: DataPaths,
-    data: Vec<u8>
+    data: Vec<u8>,
+    policy: ChunkPolicy,
+    report: Vec<String>
 }
 
 
@@ -109,7 +24,9 @@ impl Heaped for Png {
     fn new(paths: DataPaths) -> Box<Self> {
         Box::from(Png {
             paths: paths,
-            data: Vec::new()
+            data: Vec::new(),
+            policy: ChunkPolicy::from_lists(&OPTIONS.png_keep, &OPTIONS.png_drop),
+            report: Vec::new()
         })
     }
     fn inner_file_name(&self) -> String {
@@ -129,8 +46,51 @@ impl Heaped for Png {
 
     fn process(&mut self) -> Result<(), PurgeErr> {
 
-        let ancil_ranges = get_ancil_ranges(&self.data)?;
-        self.data = dont_take_ranges(&self.data, ancil_ranges);
+        let chunks = chunks::walk(&self.data)?;
+        let animation = apng::frames(&self.data, &chunks);
+        let tail = chunks.last().map_or(0, |chunk| chunk.range.end);
+
+        let mut removed: Vec<([u8; 4], usize)> = Vec::new();
+
+        let mut clean_buf: Vec<u8> = Vec::with_capacity(self.data.len());
+        clean_buf.extend_from_slice(&chunks::SIGNATURE);
+        for chunk in chunks.iter() {
+            if !self.policy.keeps(chunk) {
+                match removed.iter_mut().find(|(kind, _)| *kind == chunk.kind) {
+                    Some((_, count)) => *count += 1,
+                    None => removed.push((chunk.kind, 1)),
+                }
+                continue
+            }
+            clean_buf.extend_from_slice(&self.data[chunk.range.clone()]);
+        }
+        // Nothing may follow IEND. Whatever does is appended data or a polyglot payload.
+        let trailing = self.data.len() - tail;
+        self.data = clean_buf;
+
+        self.report.clear();
+        let mut classes: Vec<ChunkClass> = Vec::new();
+        for (kind, _) in removed.iter() {
+            let class = ChunkClass::classify(*kind);
+            if !classes.contains(&class) {
+                classes.push(class);
+            }
+        }
+        for class in classes {
+            let kinds = removed.iter()
+                .filter(|(kind, _)| ChunkClass::classify(*kind) == class)
+                .map(|(kind, count)| format!("{} x{}", String::from_utf8_lossy(kind), count))
+                .collect::<Vec<String>>();
+            self.report.push(format!("removed {} chunks: {}", class.label(), kinds.join(", ")));
+        }
+        match animation {
+            Animation::Still => {},
+            Animation::Frames(frames) => self.report.push(format!("kept animation with {} frames", frames)),
+            Animation::Damaged(problem) => self.report.push(format!("kept damaged animation as it was: {}", problem)),
+        }
+        if trailing != 0 {
+            self.report.push(format!("removed {} bytes after IEND", trailing));
+        }
 
         Ok(())
     }
@@ -152,4 +112,8 @@ impl Heaped for Png {
         // We still have to remove the temp it remove() fails
         Ok(())
     }
+
+    fn report(&self) -> Vec<String> {
+        self.report.clone()
+    }
 }
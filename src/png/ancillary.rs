@@ -0,0 +1,155 @@
+use crate::png::chunks::Chunk;
+
+/// What a chunk carries, as far as purging is concerned.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum ChunkClass {
+    /// Uppercase first letter: the image can't be decoded without it.
+    Critical,
+    /// acTL, fcTL and fdAT of an animated PNG.
+    Animation,
+    /// tEXt, zTXt and iTXt.
+    Text,
+    /// eXIf.
+    Exif,
+    /// tIME, the last modification time.
+    Time,
+    /// Chunks that change how the pixels are displayed.
+    Rendering,
+    /// Private chunks (lowercase second letter), e.g. Fireworks' mkBF or camera vendor data.
+    Private,
+    /// Any other public ancillary chunk.
+    Other,
+}
+
+const ANIMATION: [[u8; 4]; 3] = [*b"acTL", *b"fcTL", *b"fdAT"];
+const TEXT: [[u8; 4]; 3] = [*b"tEXt", *b"zTXt", *b"iTXt"];
+/// cICP, mDCv and cLLi carry the colour space and mastering display of HDR and wide gamut images.
+const RENDERING: [[u8; 4]; 9] = [*b"gAMA", *b"cHRM", *b"sRGB", *b"iCCP", *b"cICP", *b"mDCv", *b"cLLi", *b"pHYs", *b"tRNS"];
+
+impl ChunkClass {
+    pub(crate) fn classify(kind: [u8; 4]) -> ChunkClass {
+        if kind[0].is_ascii_uppercase() {
+            ChunkClass::Critical
+        } else if ANIMATION.contains(&kind) {
+            ChunkClass::Animation
+        } else if TEXT.contains(&kind) {
+            ChunkClass::Text
+        } else if &kind == b"eXIf" {
+            ChunkClass::Exif
+        } else if &kind == b"tIME" {
+            ChunkClass::Time
+        } else if RENDERING.contains(&kind) {
+            ChunkClass::Rendering
+        } else if kind[1].is_ascii_lowercase() {
+            ChunkClass::Private
+        } else {
+            ChunkClass::Other
+        }
+    }
+
+    /// How removed chunks of this class are named in the report.
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            ChunkClass::Critical => "critical",
+            ChunkClass::Animation => "animation",
+            ChunkClass::Text => "text",
+            ChunkClass::Exif => "EXIF",
+            ChunkClass::Time => "timestamp",
+            ChunkClass::Rendering => "rendering",
+            ChunkClass::Private => "private",
+            ChunkClass::Other => "other",
+        }
+    }
+}
+
+/// Which chunks survive purging.
+///
+/// Critical and animation chunks always do, in their original order. By default so do the rendering chunks
+/// (gAMA, cHRM, sRGB, iCCP, cICP, mDCv, cLLi, pHYs, tRNS); every other ancillary chunk is dropped.
+/// Single chunk types can be kept or dropped on top of that.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ChunkPolicy {
+    keep: Vec<[u8; 4]>,
+    drop: Vec<[u8; 4]>,
+}
+
+impl ChunkPolicy {
+    pub(crate) fn from_lists(keep: &[[u8; 4]], drop: &[[u8; 4]]) -> ChunkPolicy {
+        ChunkPolicy {
+            keep: keep.to_vec(),
+            drop: drop.to_vec(),
+        }
+    }
+
+    pub(crate) fn keeps(&self, chunk: &Chunk) -> bool {
+        match ChunkClass::classify(chunk.kind) {
+            ChunkClass::Critical | ChunkClass::Animation => true,
+            _ if self.drop.contains(&chunk.kind) => false,
+            _ if self.keep.contains(&chunk.kind) => true,
+            ChunkClass::Rendering => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One chunk type of each class.
+    const SAMPLES: [([u8; 4], ChunkClass); 8] = [
+        (*b"IDAT", ChunkClass::Critical),
+        (*b"fcTL", ChunkClass::Animation),
+        (*b"iTXt", ChunkClass::Text),
+        (*b"eXIf", ChunkClass::Exif),
+        (*b"tIME", ChunkClass::Time),
+        (*b"cICP", ChunkClass::Rendering),
+        (*b"mkBF", ChunkClass::Private),
+        (*b"bKGD", ChunkClass::Other),
+    ];
+
+    fn chunk(kind: [u8; 4]) -> Chunk {
+        Chunk { kind, range: 0..12 }
+    }
+
+    #[test]
+    fn classifies_each_class() {
+        for (kind, class) in SAMPLES {
+            assert_eq!(ChunkClass::classify(kind), class, "{}", String::from_utf8_lossy(&kind));
+        }
+    }
+
+    #[test]
+    fn default_keeps_critical_animation_and_rendering_chunks() {
+        let policy = ChunkPolicy::default();
+        for (kind, class) in SAMPLES {
+            let kept = matches!(class, ChunkClass::Critical | ChunkClass::Animation | ChunkClass::Rendering);
+            assert_eq!(policy.keeps(&chunk(kind)), kept, "{}", String::from_utf8_lossy(&kind));
+        }
+        for kind in RENDERING {
+            assert!(policy.keeps(&chunk(kind)), "{}", String::from_utf8_lossy(&kind));
+        }
+    }
+
+    #[test]
+    fn lists_keep_and_drop_ancillary_chunks_of_any_class() {
+        let ancillary: Vec<[u8; 4]> = SAMPLES.iter()
+            .filter(|(_, class)| !matches!(class, ChunkClass::Critical | ChunkClass::Animation))
+            .map(|(kind, _)| *kind)
+            .collect();
+        let keep_all = ChunkPolicy::from_lists(&ancillary, &[]);
+        assert!(ancillary.iter().all(|kind| keep_all.keeps(&chunk(*kind))));
+        let drop_all = ChunkPolicy::from_lists(&[], &ancillary);
+        assert!(ancillary.iter().all(|kind| !drop_all.keeps(&chunk(*kind))));
+        // Dropping wins when a type is on both lists.
+        assert!(!ChunkPolicy::from_lists(&[*b"tIME"], &[*b"tIME"]).keeps(&chunk(*b"tIME")));
+    }
+
+    #[test]
+    fn critical_and_animation_chunks_can_not_be_dropped() {
+        let policy = ChunkPolicy::from_lists(&[], &[*b"IDAT", *b"PLTE", *b"acTL", *b"fdAT"]);
+        for kind in [*b"IDAT", *b"PLTE", *b"acTL", *b"fdAT"] {
+            assert!(policy.keeps(&chunk(kind)), "{}", String::from_utf8_lossy(&kind));
+        }
+    }
+}
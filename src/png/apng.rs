@@ -0,0 +1,116 @@
+use crate::png::chunks::Chunk;
+
+const ACTL: [u8; 4] = *b"acTL";
+const FCTL: [u8; 4] = *b"fcTL";
+const FDAT: [u8; 4] = *b"fdAT";
+const IDAT: [u8; 4] = *b"IDAT";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Animation {
+    Still,
+    Frames(u32),
+    /// The animation chunks don't add up. Viewers still play what they can,
+    /// so the chunks are kept as they are and the problem is reported.
+    Damaged(&'static str),
+}
+
+fn sequence_number(chunk: &Chunk, src: &[u8]) -> Option<u32> {
+    chunk.data(src).get(..4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Checks the animation chunks of an APNG.
+///
+/// Files whose acTL comes after the first IDAT are still images: decoders show them that way.
+/// fcTL and fdAT share one sequence counter that has to run 0, 1, 2... in file order;
+/// a file that breaks it was already damaged, and purging must not look like the cause.
+pub(crate) fn frames(src: &[u8], chunks: &[Chunk]) -> Animation {
+    let actl = match chunks.iter().position(|chunk| chunk.kind == ACTL) {
+        Some(actl) => actl,
+        None => return Animation::Still,
+    };
+    if chunks[..actl].iter().any(|chunk| chunk.kind == IDAT) {
+        return Animation::Still
+    }
+    let declared = match sequence_number(&chunks[actl], src) {
+        Some(declared) => declared,
+        None => return Animation::Damaged("truncated acTL chunk"),
+    };
+
+    let mut found = 0;
+    let animation = chunks.iter().filter(|chunk| chunk.kind == FCTL || chunk.kind == FDAT);
+    for (sequence, chunk) in animation.enumerate() {
+        match sequence_number(chunk, src) {
+            Some(number) if number as usize == sequence => {},
+            Some(_) => return Animation::Damaged("animation chunks out of sequence"),
+            None => return Animation::Damaged("truncated animation chunk"),
+        }
+        if chunk.kind == FCTL {
+            found += 1;
+        }
+    }
+    if found != declared {
+        return Animation::Damaged("frame count doesn't match acTL")
+    }
+
+    Animation::Frames(declared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chunks laid out back to back with empty CRCs; `frames` only looks at types and data.
+    fn layout(chunks: &[(&[u8; 4], &[u8])]) -> (Vec<u8>, Vec<Chunk>) {
+        let mut src = Vec::new();
+        let mut walked = Vec::new();
+        for (kind, data) in chunks {
+            let start = src.len();
+            src.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            src.extend_from_slice(*kind);
+            src.extend_from_slice(data);
+            src.extend_from_slice(&[0; 4]);
+            walked.push(Chunk { kind: **kind, range: start..src.len() });
+        }
+        (src, walked)
+    }
+
+    fn animation(chunks: &[(&[u8; 4], &[u8])]) -> Animation {
+        let (src, chunks) = layout(chunks);
+        frames(&src, &chunks)
+    }
+
+    #[test]
+    fn still_images() {
+        assert_eq!(animation(&[(b"IHDR", &[]), (&IDAT, &[]), (b"IEND", &[])]), Animation::Still);
+        assert_eq!(animation(&[(&IDAT, &[]), (&ACTL, &[0, 0, 0, 1, 0, 0, 0, 0]), (b"IEND", &[])]), Animation::Still);
+    }
+
+    #[test]
+    fn counts_frames() {
+        let chunks: [(&[u8; 4], &[u8]); 5] = [
+            (&ACTL, &[0, 0, 0, 2, 0, 0, 0, 0]),
+            (&FCTL, &[0, 0, 0, 0]),
+            (&IDAT, &[]),
+            (&FCTL, &[0, 0, 0, 1]),
+            (&FDAT, &[0, 0, 0, 2]),
+        ];
+        assert_eq!(animation(&chunks), Animation::Frames(2));
+    }
+
+    #[test]
+    fn damaged_animations_are_reported_not_errors() {
+        let out_of_sequence: [(&[u8; 4], &[u8]); 4] = [
+            (&ACTL, &[0, 0, 0, 2, 0, 0, 0, 0]),
+            (&FCTL, &[0, 0, 0, 0]),
+            (&FCTL, &[0, 0, 0, 2]),
+            (&FDAT, &[0, 0, 0, 1]),
+        ];
+        assert_eq!(animation(&out_of_sequence), Animation::Damaged("animation chunks out of sequence"));
+
+        let miscounted: [(&[u8; 4], &[u8]); 2] = [(&ACTL, &[0, 0, 0, 3, 0, 0, 0, 0]), (&FCTL, &[0, 0, 0, 0])];
+        assert_eq!(animation(&miscounted), Animation::Damaged("frame count doesn't match acTL"));
+
+        assert_eq!(animation(&[(&ACTL, &[0, 0])]), Animation::Damaged("truncated acTL chunk"));
+        assert_eq!(animation(&[(&ACTL, &[0, 0, 0, 1, 0, 0, 0, 0]), (&FCTL, &[0])]), Animation::Damaged("truncated animation chunk"));
+    }
+}
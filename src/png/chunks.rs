@@ -0,0 +1,121 @@
+use std::ops::Range;
+use crate::errors::error::{PngStructureErr, PurgeErr};
+
+pub(crate) const SIGNATURE: [u8; 8] = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+pub(crate) const IEND: [u8; 4] = *b"IEND";
+
+/// One chunk of a PNG stream.
+///
+/// `range` spans the whole chunk in the source buffer: length, type, data and CRC.
+#[derive(Debug, Clone)]
+pub(crate) struct Chunk {
+    pub(crate) kind: [u8; 4],
+    pub(crate) range: Range<usize>,
+}
+
+impl Chunk {
+    /// Chunk data without the length, type and CRC.
+    pub(crate) fn data<'a>(&self, src: &'a [u8]) -> &'a [u8] {
+        &src[self.range.start + 8..self.range.end - 4]
+    }
+}
+
+fn broken(info: &str) -> PurgeErr {
+    PurgeErr::from(PngStructureErr::new(info))
+}
+
+/// Walks a PNG chunk by chunk from the signature up to and including IEND,
+/// checking every CRC on the way.
+///
+/// Bytes after IEND are not part of any chunk; `Chunk::range.end` of the last
+/// chunk tells the caller where they start.
+pub(crate) fn walk(src: &[u8]) -> Result<Vec<Chunk>, PurgeErr> {
+    if src.get(..8) != Some(&SIGNATURE) {
+        return Err(broken("not png"))
+    }
+
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut pos = SIGNATURE.len();
+
+    loop {
+        let header = src.get(pos..pos + 8).ok_or_else(|| broken("truncated chunk header"))?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let kind = [header[4], header[5], header[6], header[7]];
+        if !kind.iter().all(u8::is_ascii_alphabetic) {
+            return Err(broken("invalid chunk type"))
+        }
+
+        let crc_at = pos + 8 + length;
+        let crc = src.get(crc_at..crc_at + 4).ok_or_else(|| broken("truncated chunk"))?;
+        let expected = u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]);
+        if crc32fast::hash(&src[pos + 4..crc_at]) != expected {
+            return Err(broken(&format!("crc mismatch in {} chunk", String::from_utf8_lossy(&kind))))
+        }
+
+        let end = crc_at + 4;
+        chunks.push(Chunk { kind, range: pos..end });
+        if kind == IEND {
+            return Ok(chunks)
+        }
+        pos = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        let crc = crc32fast::hash(&chunk[4..]);
+        chunk.extend_from_slice(&crc.to_be_bytes());
+        chunk
+    }
+
+    fn sample() -> Vec<u8> {
+        let mut png = SIGNATURE.to_vec();
+        png.extend_from_slice(&chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]));
+        png.extend_from_slice(&chunk(b"tEXt", b"Author\0Jane"));
+        png.extend_from_slice(&chunk(b"IDAT", &[1, 2, 3]));
+        png.extend_from_slice(&chunk(&IEND, &[]));
+        png
+    }
+
+    #[test]
+    fn walks_chunks_up_to_iend() {
+        let mut png = sample();
+        png.extend_from_slice(b"trailing");
+        let chunks = walk(&png).expect("sample walks");
+        let kinds: Vec<&[u8; 4]> = chunks.iter().map(|chunk| &chunk.kind).collect();
+        assert_eq!(kinds, vec![b"IHDR", b"tEXt", b"IDAT", &IEND]);
+        assert_eq!(chunks[1].data(&png), b"Author\0Jane");
+        assert_eq!(chunks[0].range.start, SIGNATURE.len());
+        assert_eq!(&png[chunks[3].range.end..], b"trailing");
+    }
+
+    #[test]
+    fn truncated_files_are_errors() {
+        let png = sample();
+        for cut in 0..png.len() {
+            assert!(walk(&png[..cut]).is_err(), "cut at {}", cut);
+        }
+    }
+
+    #[test]
+    fn crc_mismatches_are_errors() {
+        let mut png = sample();
+        let data_at = SIGNATURE.len() + 25 + 8;
+        png[data_at] ^= 0xff;
+        assert!(walk(&png).is_err());
+    }
+
+    #[test]
+    fn invalid_chunk_types_are_errors() {
+        let mut png = SIGNATURE.to_vec();
+        png.extend_from_slice(&chunk(b"IH\0R", &[]));
+        assert!(walk(&png).is_err());
+        assert!(walk(b"GIF89a\0\0\0\0").is_err());
+    }
+}
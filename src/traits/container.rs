@@ -27,6 +27,11 @@ pub(crate) trait Heaped {
     fn load(&mut self) -> Result<(), PurgeErr>;
     fn process(&mut self) -> Result<(), PurgeErr>;
     fn save(&mut self) -> Result<(), PurgeErr>;
+
+    /// Human readable notes on what `process` removed, shown to the user at the end.
+    fn report(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 
@@ -48,12 +53,12 @@ impl<T: Heaped + Sized> DataBox<T> {
 
 impl<T: Heaped + Sized + Send + 'static> Purgable for DataBox<T> {
     fn load(mut self: Box<Self>) -> Result<Box<dyn Purgable>, PurgeErr> {
-        self.data.load();
+        self.data.load()?;
         Ok(self as Box<dyn Purgable>)
     }
 
     fn process(mut self: Box<Self>) -> Result<Box<dyn Purgable>, PurgeErr> {
-        self.data.process();
+        self.data.process()?;
         Ok(self as Box<dyn Purgable>)
     }
 
@@ -65,6 +70,29 @@ impl<T: Heaped + Sized + Send + 'static> Purgable for DataBox<T> {
     fn file_name(&self) -> String {
         self.file_name()
     }
+
+    fn report(&self) -> Option<UISideReport> {
+        let lines = self.data.report();
+        if lines.is_empty() {
+            return None
+        }
+        Some(UISideReport {
+            path: self.file_name(),
+            lines
+        })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct UISideReport {
+    path: String,
+    lines: Vec<String>
+}
+
+impl UISideReport {
+    pub(crate) fn ui_show(self) -> String {
+        format!("{}:\n  {}\n", self.path, self.lines.join("\n  "))
+    }
 }
 #[derive(Clone)]
 pub(crate) struct DataPaths {
@@ -132,6 +160,7 @@ pub(crate) trait Purgable: Send {
     fn save(self: Box<Self>) -> Result<(), PurgeErr>;
 
     fn file_name(&self) -> String;
+    fn report(&self) -> Option<UISideReport>;
 }
 
 